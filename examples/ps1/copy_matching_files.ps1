@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\logs\**\*.log -m line-search -s OutOfMemoryError --copy-to C:\oom-logs
+cd examples\ps1
\ No newline at end of file
@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\logs\*.log -m line-search -s OutOfMemoryError --exec-batch "tar czf oom-logs.tgz {}"
+cd examples\ps1
\ No newline at end of file
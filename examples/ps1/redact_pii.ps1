@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g .\data\*.csv -m pii --redact pseudonym --redacted-dir C:\shared
+cd examples\ps1
\ No newline at end of file
@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\Users\gilfe\Downloads\**\*.bin -m bytes -s "50 4B ?? 04"
+cd examples\ps1
\ No newline at end of file
@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g data\*.csv -m csv -s wollongong --column email --ignore-case
+cd examples\ps1
\ No newline at end of file
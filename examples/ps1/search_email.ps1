@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\exports\**\*.eml -m email -s invoice --ignore-case --attachments
+cd examples\ps1
\ No newline at end of file
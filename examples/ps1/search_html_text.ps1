@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\site\**\*.html -m text -s invoice --ignore-case --text-element headings
+cd examples\ps1
\ No newline at end of file
@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\Users\gilfe\.m2\repository\**\*.jar -m class-search -s javax.xml.bind.JAXBContext --class-element references
+cd examples\ps1
\ No newline at end of file
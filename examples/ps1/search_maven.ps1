@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\Users\gilfe\.m2\repository\**\*.jar -m maven -s org/glassfish/jersey/client/internal/LocalizationMessages
+cd examples\ps1
\ No newline at end of file
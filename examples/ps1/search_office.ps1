@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\Users\gilfe\Documents\**\*.docx -m office-search -s invoice --ignore-case
+cd examples\ps1
\ No newline at end of file
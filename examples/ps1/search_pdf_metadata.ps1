@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\Talend\**\*.pdf -m pdf-metadata -s Talend --ignore-case
+cd examples\ps1
\ No newline at end of file
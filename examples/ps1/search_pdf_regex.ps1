@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\Talend\**\*.pdf -m pdf-regex -s "\b[A-Z]{2}\d{2}[A-Z0-9]{10,30}\b" --ignore-case
+cd examples\ps1
\ No newline at end of file
@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g .\data\tb_email.csv -m pii
+cd examples\ps1
\ No newline at end of file
@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g data\*.csv -m query -s "email LIKE '%brahmakumaris%' AND venue_fk > 100"
+cd examples\ps1
\ No newline at end of file
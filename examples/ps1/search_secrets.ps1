@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\Users\gilfe\work\**\* -m secrets
+cd examples\ps1
\ No newline at end of file
@@ -0,0 +1,4 @@
+cd ..\..
+cargo build
+target\debug\file_search.exe -g C:\development\onepoint\tui\**\*.xml -m xpath -s "//target/@name"
+cd examples\ps1
\ No newline at end of file
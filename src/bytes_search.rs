@@ -0,0 +1,154 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process;
+
+use colored::Colorize;
+
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+/// Size of the blocks read from the files.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Number of bytes shown before and after every match.
+const CONTEXT_BYTES: usize = 8;
+
+/// A byte pattern where None matches any byte.
+pub(crate) type BytePattern = Vec<Option<u8>>;
+
+/// Parses hex patterns like "50 4B ?? 04" or "504B??04" or exits if the pattern is not valid.
+pub(crate) fn build_byte_pattern(search_expression: &str) -> BytePattern {
+    match parse_byte_pattern(search_expression) {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            eprintln!("Invalid byte pattern '{}': {}", search_expression, e);
+            process::exit(0x0001);
+        }
+    }
+}
+
+fn parse_byte_pattern(search_expression: &str) -> Result<BytePattern, String> {
+    let digits = search_expression.split_whitespace().collect::<String>();
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit() && *c != '?') {
+        return Err(format!("'{}' is not a hex digit", c));
+    }
+    if digits.is_empty() {
+        return Err("the pattern is empty".to_string());
+    }
+    if digits.len() % 2 != 0 {
+        return Err("every byte needs two hex digits".to_string());
+    }
+    let pattern = (0..digits.len()).step_by(2)
+        .map(|i| match &digits[i..i + 2] {
+            "??" => Ok(None),
+            pair => u8::from_str_radix(pair, 16).map(Some).map_err(|_| format!("'{}' is not a hex byte", pair))
+        })
+        .collect::<Result<BytePattern, String>>()?;
+    if pattern.iter().all(|b| b.is_none()) {
+        return Err("the pattern only has wildcards".to_string());
+    }
+    Ok(pattern)
+}
+
+/// Prints "file :: offset :: hex context :: ascii context" for every occurrence of the pattern.
+/// Files are read in blocks, keeping enough of the previous block to find matches and show context across blocks.
+pub(crate) fn process_bytes_search(path: PathBuf, pattern: &BytePattern, output: &mut dyn OutputPrinter) {
+    if path.is_dir() {
+        return;
+    }
+    let file_name = path.to_str().unwrap();
+    if let Err(e) = scan_file(file_name, pattern, output) {
+        output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str());
+    }
+}
+
+fn scan_file(file_name: &str, pattern: &BytePattern, output: &mut dyn OutputPrinter) -> std::io::Result<()> {
+    let mut file = File::open(file_name)?;
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    let mut window: Vec<u8> = vec![];
+    let mut window_start: u64 = 0;
+    let mut next = 0;
+    let highlight = output.get_name().eq("StdPrinter");
+    loop {
+        let read = file.read(&mut chunk)?;
+        let eof = read == 0;
+        window.extend_from_slice(&chunk[..read]);
+        // Until the end of the file, matches are only looked for where the context after them has been read
+        let needed = if eof { pattern.len() } else { pattern.len() + CONTEXT_BYTES };
+        let end = (window.len() + 1).saturating_sub(needed);
+        for position in next..end {
+            if matches_at(&window, position, pattern) {
+                let offset = window_start + position as u64;
+                let (hex, ascii) = hex_context(&window, position, pattern.len(), highlight);
+                let fields = HitFields {
+                    offset: Some(offset),
+                    matched: Some(hex_of(&window[position..position + pattern.len()])),
+                    ..HitFields::default()
+                };
+                output.output_hit(format!("{} :: 0x{:08x} :: {} :: {}", file_name, offset, hex, ascii).as_str(),
+                                  fields);
+            }
+        }
+        next = next.max(end);
+        if eof {
+            return Ok(());
+        }
+        let drop = next.saturating_sub(CONTEXT_BYTES);
+        window.drain(..drop);
+        window_start += drop as u64;
+        next -= drop;
+    }
+}
+
+fn matches_at(window: &[u8], position: usize, pattern: &BytePattern) -> bool {
+    pattern.iter().enumerate().all(|(i, expected)| match expected {
+        Some(b) => window[position + i] == *b,
+        None => true
+    })
+}
+
+/// Formats the match with the bytes around it in hex and as ascii, like a hexdump. The matched bytes are
+/// highlighted on the console and enclosed in brackets otherwise.
+fn hex_context(window: &[u8], position: usize, length: usize, highlight: bool) -> (String, String) {
+    let start = position.saturating_sub(CONTEXT_BYTES);
+    let end = (position + length + CONTEXT_BYTES).min(window.len());
+    let before = hex_of(&window[start..position]);
+    let matched = hex_of(&window[position..position + length]);
+    let after = hex_of(&window[position + length..end]);
+    let matched = if highlight { matched.red().bold().to_string() } else { format!("[{}]", matched) };
+    let hex = [before, matched, after].iter()
+        .filter(|s| !s.is_empty())
+        .cloned()
+        .collect::<Vec<String>>()
+        .join(" ");
+    let ascii = window[start..end].iter()
+        .map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' })
+        .collect::<String>();
+    (hex, format!("|{}|", ascii))
+}
+
+fn hex_of(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_spaced_and_compact_patterns() {
+        assert_eq!(parse_byte_pattern("50 4B ?? 04"), Ok(vec![Some(0x50), Some(0x4b), None, Some(0x04)]));
+        assert_eq!(parse_byte_pattern("504b??04"), Ok(vec![Some(0x50), Some(0x4b), None, Some(0x04)]));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert_eq!(parse_byte_pattern("50 4G"), Err("'G' is not a hex digit".to_string()));
+        assert_eq!(parse_byte_pattern("ä0"), Err("'ä' is not a hex digit".to_string()));
+        assert_eq!(parse_byte_pattern("  "), Err("the pattern is empty".to_string()));
+        assert_eq!(parse_byte_pattern("504"), Err("every byte needs two hex digits".to_string()));
+        assert_eq!(parse_byte_pattern("5?"), Err("'5?' is not a hex byte".to_string()));
+        assert_eq!(parse_byte_pattern("?? ??"), Err("the pattern only has wildcards".to_string()));
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Seek};
+use std::path::PathBuf;
+
+use zip::ZipArchive;
+
+use crate::cli::{ClassElement, Cli};
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+const CLASS_MAGIC: u32 = 0xCAFE_BABE;
+
+/// Extensions of archives nested in other archives which are searched too, like the libraries of a war.
+const NESTED_ARCHIVE_EXTENSIONS: [&str; 4] = [".jar", ".war", ".ear", ".zip"];
+
+pub(crate) fn process_class_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    // Class names are shown with dots, so names in the internal form with slashes are converted
+    let search_expression = cli.search_expression.as_ref().unwrap().replace('/', ".");
+    if needs_regex(cli) {
+        let re = build_regex(cli, &search_expression, true);
+        process_class_search_generic(path, cli, &re, find_regex, output);
+    } else {
+        process_class_search_generic(path, cli, &search_expression, find_simple, output);
+    }
+}
+
+/// Prints "archive :: entry :: element :: value" for every matching element of the classes in jar, war, ear and
+/// zip archives and "file :: element :: value" for class files.
+fn process_class_search_generic<T>(path: PathBuf, cli: &Cli, search_filter: &T,
+                                   find_fn: fn(&str, &T) -> bool,
+                                   output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().unwrap();
+    if file_name.to_lowercase().ends_with(".class") {
+        match fs::read(&path) {
+            Ok(bytes) => search_class(file_name, None, &bytes, cli, search_filter, find_fn, output),
+            Err(e) => output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str())
+        }
+        return;
+    }
+    let archive = File::open(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|f| ZipArchive::new(f).map_err(|e| e.to_string()));
+    match archive {
+        Ok(mut archive) => search_archive(file_name, None, &mut archive, cli, search_filter, find_fn, output),
+        Err(e) => output.err_output(format!("{} :: {}", file_name, e).as_str())
+    }
+}
+
+/// Searches the classes of the archive. Entries of nested archives are named like "lib/a.jar!/A.class".
+fn search_archive<R: Read + Seek, T>(location: &str, parent_entry: Option<&str>, archive: &mut ZipArchive<R>,
+                                     cli: &Cli, search_filter: &T,
+                                     find_fn: fn(&str, &T) -> bool,
+                                     output: &mut dyn OutputPrinter) {
+    for i in 0..archive.len() {
+        let mut entry = match archive.by_index(i) {
+            Ok(entry) => entry,
+            Err(e) => {
+                output.err_output(format!("{} :: {}", location, e).as_str());
+                continue;
+            }
+        };
+        let entry_name = entry.name().to_string();
+        let lower_name = entry_name.to_lowercase();
+        let is_class = lower_name.ends_with(".class");
+        let is_archive = NESTED_ARCHIVE_EXTENSIONS.iter().any(|e| lower_name.ends_with(e));
+        if !is_class && !is_archive {
+            continue;
+        }
+        let entry_location = format!("{} :: {}", location, entry_name);
+        let entry_path = match parent_entry {
+            Some(parent_entry) => format!("{}!/{}", parent_entry, entry_name),
+            None => entry_name.clone(),
+        };
+        let mut bytes = vec![];
+        if let Err(e) = entry.read_to_end(&mut bytes) {
+            output.err_output(format!("{} :: {}", entry_location, e).as_str());
+            continue;
+        }
+        if is_class {
+            search_class(&entry_location, Some(&entry_path), &bytes, cli, search_filter, find_fn, output);
+        } else {
+            match ZipArchive::new(Cursor::new(bytes)) {
+                Ok(mut nested) => search_archive(&entry_location, Some(&entry_path), &mut nested, cli, search_filter,
+                                                 find_fn, output),
+                Err(e) => output.err_output(format!("{} :: {}", entry_location, e).as_str())
+            }
+        }
+    }
+}
+
+fn search_class<T>(location: &str, entry: Option<&str>, bytes: &[u8], cli: &Cli, search_filter: &T,
+                   find_fn: fn(&str, &T) -> bool,
+                   output: &mut dyn OutputPrinter) {
+    match parse_class(bytes) {
+        Ok(class) => {
+            for (element, value) in class.elements() {
+                let selected = cli.class_element.is_empty() || cli.class_element.contains(&element);
+                if selected && find_fn(value, search_filter) {
+                    let fields = HitFields {
+                        entry: entry.map(|e| e.to_string()),
+                        matched: Some(value.to_string()),
+                        ..HitFields::default()
+                    };
+                    output.output_hit(format!("{} :: {} :: {}", location, element_name(&element), value).as_str(),
+                                      fields);
+                }
+            }
+        }
+        Err(e) => output.err_output(format!("{} :: {}", location, e).as_str())
+    }
+}
+
+fn element_name(element: &ClassElement) -> &'static str {
+    match element {
+        ClassElement::Class => "class",
+        ClassElement::Extends => "extends",
+        ClassElement::Implements => "implements",
+        ClassElement::Field => "field",
+        ClassElement::Method => "method",
+        ClassElement::References => "references",
+    }
+}
+
+/// The searchable parts of a class file, with class names in the source form, e.g. java.util.Map$Entry.
+struct ClassInfo {
+    name: String,
+    super_class: Option<String>,
+    interfaces: Vec<String>,
+    fields: Vec<String>,
+    methods: Vec<String>,
+    references: BTreeSet<String>,
+}
+
+impl ClassInfo {
+    fn elements(&self) -> Vec<(ClassElement, &str)> {
+        let mut elements = vec![(ClassElement::Class, self.name.as_str())];
+        elements.extend(self.super_class.iter().map(|s| (ClassElement::Extends, s.as_str())));
+        elements.extend(self.interfaces.iter().map(|s| (ClassElement::Implements, s.as_str())));
+        elements.extend(self.fields.iter().map(|s| (ClassElement::Field, s.as_str())));
+        elements.extend(self.methods.iter().map(|s| (ClassElement::Method, s.as_str())));
+        elements.extend(self.references.iter().map(|s| (ClassElement::References, s.as_str())));
+        elements
+    }
+}
+
+enum Constant {
+    Utf8(String),
+    Class(u16),
+    NameAndType(u16),
+    MethodType(u16),
+    Other,
+}
+
+struct ClassReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl ClassReader<'_> {
+    fn take(&mut self, length: usize) -> Result<&[u8], String> {
+        let end = self.position + length;
+        if end > self.bytes.len() {
+            return Err("Unexpected end of class file".to_string());
+        }
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u1(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u2(&mut self) -> Result<u16, String> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u4(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Skips the attributes of a field, method or class.
+    fn skip_attributes(&mut self) -> Result<(), String> {
+        for _ in 0..self.u2()? {
+            self.u2()?;
+            let length = self.u4()? as usize;
+            self.take(length)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_class(bytes: &[u8]) -> Result<ClassInfo, String> {
+    let mut reader = ClassReader { bytes, position: 0 };
+    if reader.u4()? != CLASS_MAGIC {
+        return Err("Not a class file".to_string());
+    }
+    reader.take(4)?;
+    let constants = parse_constant_pool(&mut reader)?;
+    let utf8 = |index: u16| match constants.get(index as usize) {
+        Some(Constant::Utf8(s)) => Ok(s.as_str()),
+        _ => Err(format!("Invalid constant pool reference {}", index))
+    };
+    let class_name = |index: u16| match constants.get(index as usize) {
+        Some(Constant::Class(name_index)) => utf8(*name_index).map(|name| name.replace('/', ".")),
+        _ => Err(format!("Invalid class reference {}", index))
+    };
+    reader.u2()?;
+    let name = class_name(reader.u2()?)?;
+    let super_index = reader.u2()?;
+    let super_class = if super_index == 0 { None } else { Some(class_name(super_index)?) };
+    let mut interfaces = vec![];
+    for _ in 0..reader.u2()? {
+        interfaces.push(class_name(reader.u2()?)?);
+    }
+    let mut references = BTreeSet::new();
+    let mut fields = vec![];
+    for _ in 0..reader.u2()? {
+        let access = reader.u2()?;
+        let field_name = utf8(reader.u2()?)?;
+        let descriptor = utf8(reader.u2()?)?;
+        reader.skip_attributes()?;
+        collect_descriptor_classes(descriptor, &mut references);
+        let mut chars = descriptor.chars().peekable();
+        fields.push(declaration(&field_modifiers(access), &format!("{} {}", type_name(&mut chars), field_name)));
+    }
+    let mut methods = vec![];
+    for _ in 0..reader.u2()? {
+        let access = reader.u2()?;
+        let method_name = utf8(reader.u2()?)?;
+        let descriptor = utf8(reader.u2()?)?;
+        reader.skip_attributes()?;
+        collect_descriptor_classes(descriptor, &mut references);
+        methods.push(declaration(&method_modifiers(access), &method_signature(method_name, descriptor)));
+    }
+    for constant in &constants {
+        match constant {
+            Constant::Class(name_index) => {
+                let referenced = utf8(*name_index)?;
+                if referenced.starts_with('[') {
+                    collect_descriptor_classes(referenced, &mut references);
+                } else {
+                    references.insert(referenced.replace('/', "."));
+                }
+            }
+            Constant::NameAndType(descriptor_index) | Constant::MethodType(descriptor_index) => {
+                collect_descriptor_classes(utf8(*descriptor_index)?, &mut references);
+            }
+            _ => {}
+        }
+    }
+    references.remove(&name);
+    Ok(ClassInfo { name, super_class, interfaces, fields, methods, references })
+}
+
+/// Reads the constant pool, indexed from 1. Long and double constants take two slots.
+fn parse_constant_pool(reader: &mut ClassReader) -> Result<Vec<Constant>, String> {
+    let count = reader.u2()? as usize;
+    let mut constants = vec![Constant::Other];
+    while constants.len() < count {
+        let tag = reader.u1()?;
+        let constant = match tag {
+            1 => {
+                let length = reader.u2()? as usize;
+                // Modified UTF-8 only differs from UTF-8 for the null character and supplementary characters
+                Constant::Utf8(String::from_utf8_lossy(reader.take(length)?).to_string())
+            }
+            7 => Constant::Class(reader.u2()?),
+            12 => {
+                reader.u2()?;
+                Constant::NameAndType(reader.u2()?)
+            }
+            16 => Constant::MethodType(reader.u2()?),
+            3 | 4 | 9 | 10 | 11 | 17 | 18 => {
+                reader.take(4)?;
+                Constant::Other
+            }
+            5 | 6 => {
+                reader.take(8)?;
+                constants.push(Constant::Other);
+                Constant::Other
+            }
+            8 | 19 | 20 => {
+                reader.take(2)?;
+                Constant::Other
+            }
+            15 => {
+                reader.take(3)?;
+                Constant::Other
+            }
+            _ => return Err(format!("Unknown constant pool tag {}", tag))
+        };
+        constants.push(constant);
+    }
+    Ok(constants)
+}
+
+/// Adds the classes of a field or method descriptor, like (Ljava/lang/String;[I)Ljava/util/List;
+fn collect_descriptor_classes(descriptor: &str, references: &mut BTreeSet<String>) {
+    let mut rest = descriptor;
+    while let Some(start) = rest.find('L') {
+        // Class names only start after the type characters, so a previous L is always closed by ;
+        let preceding_ok = rest[..start].chars().all(|c| "()[BCDFIJSZV".contains(c));
+        if !preceding_ok {
+            break;
+        }
+        match rest[start..].find(';') {
+            Some(end) => {
+                references.insert(rest[start + 1..start + end].replace('/', "."));
+                rest = &rest[start + end + 1..];
+            }
+            None => break
+        }
+    }
+}
+
+/// Converts the next type of a descriptor to its source form, e.g. [Ljava/lang/String; to java.lang.String[].
+fn type_name(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    match chars.next() {
+        Some('B') => "byte".to_string(),
+        Some('C') => "char".to_string(),
+        Some('D') => "double".to_string(),
+        Some('F') => "float".to_string(),
+        Some('I') => "int".to_string(),
+        Some('J') => "long".to_string(),
+        Some('S') => "short".to_string(),
+        Some('Z') => "boolean".to_string(),
+        Some('V') => "void".to_string(),
+        Some('[') => format!("{}[]", type_name(chars)),
+        Some('L') => chars.by_ref().take_while(|c| *c != ';').collect::<String>().replace('/', "."),
+        _ => "?".to_string()
+    }
+}
+
+/// Formats a method like "java.util.List foo(int, java.lang.String)".
+fn method_signature(name: &str, descriptor: &str) -> String {
+    let mut chars = descriptor.chars().peekable();
+    let mut parameters = vec![];
+    if chars.next() == Some('(') {
+        while chars.peek().map(|c| *c != ')').unwrap_or(false) {
+            parameters.push(type_name(&mut chars));
+        }
+        chars.next();
+    }
+    format!("{} {}({})", type_name(&mut chars), name, parameters.join(", "))
+}
+
+fn declaration(modifiers: &[&str], rest: &str) -> String {
+    if modifiers.is_empty() { rest.to_string() } else { format!("{} {}", modifiers.join(" "), rest) }
+}
+
+fn field_modifiers(access: u16) -> Vec<&'static str> {
+    modifiers(access, &[(0x0001, "public"), (0x0002, "private"), (0x0004, "protected"), (0x0008, "static"),
+        (0x0010, "final"), (0x0040, "volatile"), (0x0080, "transient")])
+}
+
+fn method_modifiers(access: u16) -> Vec<&'static str> {
+    modifiers(access, &[(0x0001, "public"), (0x0002, "private"), (0x0004, "protected"), (0x0008, "static"),
+        (0x0010, "final"), (0x0020, "synchronized"), (0x0100, "native"), (0x0400, "abstract")])
+}
+
+fn modifiers(access: u16, flags: &[(u16, &'static str)]) -> Vec<&'static str> {
+    flags.iter().filter(|(flag, _)| access & flag != 0).map(|(_, name)| *name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf8(text: &str) -> Vec<u8> {
+        let mut bytes = vec![1];
+        bytes.extend((text.len() as u16).to_be_bytes());
+        bytes.extend(text.as_bytes());
+        bytes
+    }
+
+    /// A class com.example.Foo extending java.lang.Object without members. The long constant takes two slots,
+    /// so the name and type after it is constant 9.
+    fn class_bytes() -> Vec<u8> {
+        let mut bytes = CLASS_MAGIC.to_be_bytes().to_vec();
+        bytes.extend([0, 0, 0, 52]);
+        bytes.extend(10u16.to_be_bytes());
+        bytes.extend(utf8("com/example/Foo"));
+        bytes.extend([7, 0, 1]);
+        bytes.extend(utf8("java/lang/Object"));
+        bytes.extend([7, 0, 3]);
+        bytes.extend([5, 0, 0, 0, 0, 0, 0, 0, 42]);
+        bytes.extend(utf8("(Ljava/util/List;[Ljava/time/Instant;)V"));
+        bytes.extend(utf8("run"));
+        bytes.extend([12, 0, 8, 0, 7]);
+        bytes.extend([0, 0x21, 0, 2, 0, 4]);
+        bytes.extend([0, 0, 0, 0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn long_constants_take_two_slots() {
+        let bytes = class_bytes();
+        let mut reader = ClassReader { bytes: &bytes, position: 8 };
+        let constants = parse_constant_pool(&mut reader).unwrap();
+        assert_eq!(constants.len(), 10);
+        assert!(matches!(&constants[7], Constant::Utf8(s) if s.starts_with("(Ljava/util/List;")));
+        assert!(matches!(constants[9], Constant::NameAndType(7)));
+    }
+
+    #[test]
+    fn reads_names_and_referenced_classes() {
+        let info = parse_class(&class_bytes()).unwrap();
+        assert_eq!(info.name, "com.example.Foo");
+        assert_eq!(info.super_class.as_deref(), Some("java.lang.Object"));
+        assert_eq!(info.references.iter().map(|s| s.as_str()).collect::<Vec<&str>>(),
+                   vec!["java.lang.Object", "java.time.Instant", "java.util.List"]);
+    }
+
+    #[test]
+    fn rejects_truncated_and_unknown_constants() {
+        let bytes = class_bytes();
+        assert_eq!(parse_class(&bytes[..20]).err(), Some("Unexpected end of class file".to_string()));
+        let mut unknown = bytes[..10].to_vec();
+        unknown.push(2);
+        assert_eq!(parse_class(&unknown).err(), Some("Unknown constant pool tag 2".to_string()));
+        assert_eq!(parse_class(b"not a class").err(), Some("Not a class file".to_string()));
+    }
+}
@@ -9,7 +9,78 @@ pub(crate) enum Mode {
     LineRegexSearch,
     ZipRegex,
     JsonPath,
-    PdfSearch
+    PdfSearch,
+    PdfRegex,
+    PdfMetadata,
+    OfficeSearch,
+    Xpath,
+    Csv,
+    Query,
+    Sqlite,
+    Email,
+    Text,
+    ClassSearch,
+    Maven,
+    Duplicates,
+    Dedupe,
+    Bytes,
+    Secrets,
+    Pii
+}
+
+#[derive(ArgEnum, Debug, Clone)]
+#[clap(rename_all = "kebab_case")]
+pub(crate) enum EmailPart {
+    Headers,
+    Body
+}
+
+#[derive(ArgEnum, Debug, Clone)]
+#[clap(rename_all = "kebab_case")]
+pub(crate) enum TextElement {
+    Visible,
+    Headings,
+    Links,
+    Alt
+}
+
+#[derive(ArgEnum, Debug, Clone, PartialEq)]
+#[clap(rename_all = "kebab_case")]
+pub(crate) enum ClassElement {
+    Class,
+    Extends,
+    Implements,
+    Field,
+    Method,
+    References
+}
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq)]
+#[clap(rename_all = "kebab_case")]
+pub(crate) enum PiiType {
+    Email,
+    Phone,
+    Iban,
+    CreditCard,
+    NationalId,
+    Ip
+}
+
+#[derive(ArgEnum, Debug, Clone)]
+#[clap(rename_all = "kebab_case")]
+pub(crate) enum RedactStrategy {
+    Mask,
+    Hash,
+    Pseudonym
+}
+
+#[derive(ArgEnum, Debug, Clone)]
+#[clap(rename_all = "kebab_case")]
+pub(crate) enum ConflictStrategy {
+    Skip,
+    Overwrite,
+    Rename,
+    Fail
 }
 
 #[derive(ArgEnum, Debug, Clone)]
@@ -17,12 +88,15 @@ pub(crate) enum Mode {
 pub(crate) enum Output {
     Console,
     File,
-    HTML
+    Html,
+    Json
 }
 
 /**
 Simple binary programme used to grep files by name, or for searching inside of compressed files.
 JSON path is also supported when using json-path mode. If using json path, it should be a valid json path expression.
+Files with the yaml, yml or toml extension are converted to json before the json path is applied.
+The query mode filters the records of csv, json and json lines files with expressions like "age > 30 AND country = 'UK'".
 */
 #[derive(Parser)]
 pub(crate) struct Cli {
@@ -45,4 +119,155 @@ pub(crate) struct Cli {
     /// The output file in case the output parameter is "file". See the "output" parameter.
     #[clap(short, long)]
     pub(crate) file: Option<String>,
+
+    /// Ignore case when matching the search expression. Applies to all modes matching text, in json-path mode to the
+    /// value filter. Not supported by the file-name, zip, zip-regex, xpath and bytes modes.
+    #[clap(short, long)]
+    pub(crate) ignore_case: bool,
+
+    /// Only match the search expression as a whole word. Applies to all modes matching text, in json-path mode to
+    /// the value filter. Not supported by the file-name, zip, zip-regex, xpath, bytes and query modes.
+    #[clap(short, long)]
+    pub(crate) word: bool,
+
+    /// Directory of the cache for text extracted from pdf, office, html and markdown files and pdf attachments of
+    /// emails. No cache is used if not set.
+    #[clap(long)]
+    pub(crate) cache_dir: Option<String>,
+
+    /// Removes all entries from the cache directory before searching. Requires --cache-dir.
+    #[clap(long)]
+    pub(crate) clear_cache: bool,
+
+    /// Maximum size of the cache directory in megabytes. The oldest entries are removed after searching.
+    #[clap(long)]
+    pub(crate) cache_max_size: Option<u64>,
+
+    /// Namespace prefix binding used by the xpath mode, e.g. 'm=http://maven.apache.org/POM/4.0.0'. Can be repeated.
+    #[clap(long, multiple_occurrences(true))]
+    pub(crate) namespace: Vec<String>,
+
+    /// Treat every line of the files as a separate json document in json-path and query mode (NDJSON / JSON Lines).
+    /// Files with the ndjson or jsonl extension are always read like this.
+    #[clap(long)]
+    pub(crate) json_lines: bool,
+
+    /// Pretty print the json values matched in json-path mode. Values are printed on a single line otherwise.
+    #[clap(long)]
+    pub(crate) pretty: bool,
+
+    /// Only report json-path matches whose value passes this filter: literal text like 'DE', a regular expression
+    /// like 're:^D[EK]$' or a numeric comparison like '>= 30' (operators >, >=, <, <=, ==, !=).
+    #[clap(long)]
+    pub(crate) value_filter: Option<String>,
+
+    /// Column searched in csv mode, given by header name or 1 based index. Can be repeated. All columns are searched if not set.
+    #[clap(long, multiple_occurrences(true))]
+    pub(crate) column: Vec<String>,
+
+    /// Field delimiter in csv and query mode, e.g. ';' or 'tab'. Detected from the header line if not set.
+    #[clap(long)]
+    pub(crate) delimiter: Option<String>,
+
+    /// Quote character in csv and query mode. Detected from the first lines if not set.
+    #[clap(long)]
+    pub(crate) quote: Option<String>,
+
+    /// Table searched in sqlite mode. Can be repeated. All tables are searched if not set.
+    #[clap(long, multiple_occurrences(true))]
+    pub(crate) table: Vec<String>,
+
+    /// Restricts the email mode to the headers or to the bodies of the messages. Both are searched if not set.
+    #[clap(long, arg_enum)]
+    pub(crate) email_part: Option<EmailPart>,
+
+    /// Also search the attachments of the messages in email mode: text, pdf and the entry names of zip files.
+    #[clap(long)]
+    pub(crate) attachments: bool,
+
+    /// Text searched in text mode: visible text, headings, links (text and target) or alt texts. Can be repeated.
+    /// Only visible text is searched if not set.
+    #[clap(long, arg_enum, multiple_occurrences(true))]
+    pub(crate) text_element: Vec<TextElement>,
+
+    /// Part of the classes searched in class-search mode: the class name, super class, interfaces, field and method
+    /// declarations or referenced classes. Can be repeated. All parts are searched if not set.
+    #[clap(long, arg_enum, multiple_occurrences(true))]
+    pub(crate) class_element: Vec<ClassElement>,
+
+    /// Compare the CRC32 and size of duplicate entries in duplicates mode to tell identical copies from conflicts.
+    #[clap(long)]
+    pub(crate) compare_content: bool,
+
+    /// Also compare the entries of zip, jar, war and ear archives in dedupe mode, using the CRC32 stored in the archive.
+    #[clap(long)]
+    pub(crate) include_archives: bool,
+
+    /// Toml or json file with additional rules for secrets mode. Every rule has an id, a regex whose first group
+    /// (or whole match) is the secret and an optional minimum entropy. Rules replace built-in rules with the same id.
+    #[clap(long)]
+    pub(crate) rules: Option<String>,
+
+    /// Kind of personal data reported in pii mode: email addresses, phone numbers, IBANs, credit card numbers,
+    /// national IDs (US social security and UK national insurance numbers) or IP addresses. Can be repeated.
+    /// All kinds are reported if not set.
+    #[clap(long, arg_enum, multiple_occurrences(true))]
+    pub(crate) pii_type: Vec<PiiType>,
+
+    /// Directory to which the redacted copies are written, which must not contain the searched files. The copies
+    /// keep their path below the directory of the glob pattern. Without --redact, pii mode writes copies with the findings replaced by their kind, e.g. [EMAIL].
+    #[clap(long)]
+    pub(crate) redacted_dir: Option<String>,
+
+    /// Write a copy of every file with matches to --redacted-dir, with the matches replaced by asterisks, the start
+    /// of their keyed hash (HMAC-SHA256) or a pseudonym which is the same for every occurrence of a value. Zip, jar, war and ear
+    /// archives are rewritten with their text entries redacted. Supported in line-search, line-regex-search, secrets
+    /// and pii mode.
+    #[clap(long, arg_enum)]
+    pub(crate) redact: Option<RedactStrategy>,
+
+    /// Key of the hash of --redact hash, to get the same hashes in several runs. A random key is used for every run if
+    /// not set. Keep it secret, as values like phone numbers can be found from their hash with the key.
+    #[clap(long)]
+    pub(crate) redact_key: Option<String>,
+
+    /// Csv file to which the redacted values and their replacements are written. Nothing is written if not set.
+    #[clap(long)]
+    pub(crate) redact_mapping: Option<String>,
+
+    /// Command run for every file with hits, e.g. 'gzip {}'. {} and {path} are replaced by the file. With {entry}
+    /// (the archive entry), {line} (the line, counted from 1), {offset} (the byte offset in bytes mode) or {match}
+    /// (the matching text, the path in file-name mode) the command runs for every hit instead; they are empty if the mode does not know them. Non-zero exit statuses are counted as errors.
+    #[clap(long)]
+    pub(crate) exec: Option<String>,
+
+    /// Command run once after searching with all files with hits in place of {}, or appended if there is no {}.
+    #[clap(long)]
+    pub(crate) exec_batch: Option<String>,
+
+    /// Maximum number of commands of --exec running at the same time. Defaults to 1.
+    #[clap(long)]
+    pub(crate) exec_jobs: Option<usize>,
+
+    /// Copy the files with hits to this directory, keeping their path below the directory of the glob pattern.
+    #[clap(long)]
+    pub(crate) copy_to: Option<String>,
+
+    /// Move the files with hits to this directory, keeping their path below the directory of the glob pattern.
+    #[clap(long)]
+    pub(crate) move_to: Option<String>,
+
+    /// Delete the files with hits.
+    #[clap(long)]
+    pub(crate) delete: bool,
+
+    /// Apply --copy-to, --move-to or --delete without asking. Otherwise the planned changes are listed and only
+    /// applied after confirming on the terminal.
+    #[clap(long)]
+    pub(crate) yes: bool,
+
+    /// What --copy-to and --move-to do with files which exist already: skip the file, overwrite the existing file,
+    /// add a number to the name or change nothing at all. Defaults to skip. Files with hits are never overwritten.
+    #[clap(long, arg_enum)]
+    pub(crate) on_conflict: Option<ConflictStrategy>,
 }
\ No newline at end of file
@@ -0,0 +1,196 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use csv::{Reader, ReaderBuilder, StringRecord};
+
+use crate::cli::Cli;
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+/// Delimiters which are considered when the delimiter is detected from the header line.
+const CANDIDATE_DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+pub(crate) fn process_csv_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    let search_expression = cli.search_expression.as_ref().unwrap();
+    if needs_regex(cli) {
+        let re = build_regex(cli, search_expression, true);
+        process_csv_search_generic(path, cli, &re, find_regex, output);
+    } else {
+        process_csv_search_generic(path, cli, search_expression, find_simple, output);
+    }
+}
+
+/// Prints "file :: row :: column :: value :: record" for every matching field of the selected columns.
+/// Rows are numbered from 1, not counting the header. Fields which are not UTF-8 are decoded lossily.
+fn process_csv_search_generic<T>(path: PathBuf, cli: &Cli, search_filter: &T,
+                                 find_fn: fn(&str, &T) -> bool,
+                                 output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().unwrap();
+    let mut reader = match open_csv_reader(&path, cli) {
+        Ok(reader) => reader,
+        Err(e) => {
+            output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str());
+            return;
+        }
+    };
+    let headers = match reader.byte_headers() {
+        Ok(headers) => StringRecord::from_byte_record_lossy(headers.clone()),
+        Err(e) => {
+            output.err_output(format!("{} :: Could not read header: {}", file_name, e).as_str());
+            return;
+        }
+    };
+    let columns = match select_columns(&headers, &cli.column) {
+        Ok(columns) => columns,
+        Err(e) => {
+            output.err_output(format!("{} :: {}", file_name, e).as_str());
+            return;
+        }
+    };
+    for (index, record) in reader.byte_records().enumerate() {
+        let row = index + 1;
+        match record {
+            Ok(record) => {
+                let record = StringRecord::from_byte_record_lossy(record);
+                for column in &columns {
+                    let value = record.get(*column).unwrap_or("");
+                    if find_fn(value, search_filter) {
+                        let fields = HitFields { line: Some(row as u64), matched: Some(value.to_string()),
+                                                 ..HitFields::default() };
+                        output.output_hit(format!("{} :: {} :: {} :: {} :: {}", file_name, row,
+                                                  headers.get(*column).unwrap_or(""), value,
+                                                  format_record(&headers, &record)).as_str(), fields);
+                    }
+                }
+            }
+            Err(e) => {
+                output.err_output(format!("{} :: {} :: {}", file_name, row, e).as_str());
+            }
+        }
+    }
+}
+
+/// Opens a reader with the delimiter and quote character of the command line or detected from the file.
+pub(crate) fn open_csv_reader(path: &Path, cli: &Cli) -> Result<Reader<File>, String> {
+    let (delimiter, quote) = detect_format(path, cli).map_err(|e| e.to_string())?;
+    ReaderBuilder::new()
+        .delimiter(delimiter)
+        .quote(quote)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| e.to_string())
+}
+
+/// Resolves the columns given by header name or by 1 based index. All columns are selected if none are given.
+fn select_columns(headers: &StringRecord, selection: &[String]) -> Result<Vec<usize>, String> {
+    if selection.is_empty() {
+        return Ok((0..headers.len()).collect());
+    }
+    selection.iter()
+        .map(|column| {
+            headers.iter().position(|h| h.trim() == column.trim())
+                .or_else(|| column.parse::<usize>().ok().filter(|i| *i >= 1 && *i <= headers.len()).map(|i| i - 1))
+                .ok_or_else(|| format!("Column '{}' not found in header", column))
+        })
+        .collect()
+}
+
+fn format_record(headers: &StringRecord, record: &StringRecord) -> String {
+    record.iter().enumerate()
+        .map(|(i, value)| format!("{}={}", headers.get(i).unwrap_or(""), value))
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+/// Number of lines looked at when detecting the quote character.
+const SAMPLE_LINES: usize = 20;
+
+/// The first lines of the file, decoded lossily so that files in e.g. Latin-1 can be detected as well.
+fn read_sample(path: &Path) -> std::io::Result<Vec<String>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut sample = vec![];
+    let mut line = vec![];
+    while sample.len() < SAMPLE_LINES && reader.read_until(b'\n', &mut line)? > 0 {
+        let text = String::from_utf8_lossy(&line);
+        sample.push(text.trim_end_matches(['\r', '\n']).to_string());
+        line.clear();
+    }
+    Ok(sample)
+}
+
+/// Determines delimiter and quote character. Options of the command line take precedence.
+/// Otherwise the delimiter is the candidate occurring most often outside of double quotes in the header line
+/// and the quote is the character, double or single quote, which starts more fields in the first lines.
+fn detect_format(path: &Path, cli: &Cli) -> std::io::Result<(u8, u8)> {
+    let sample = read_sample(path)?;
+    let header = sample.first().map(|h| h.as_str()).unwrap_or("");
+    let delimiter = match &cli.delimiter {
+        Some(d) if d == "\\t" || d == "tab" => b'\t',
+        Some(d) => d.bytes().next().unwrap_or(b','),
+        None => detect_delimiter(header)
+    };
+    let quote = match &cli.quote {
+        Some(q) => q.bytes().next().unwrap_or(b'"'),
+        None => detect_quote(&sample, delimiter)
+    };
+    Ok((delimiter, quote))
+}
+
+fn detect_delimiter(header: &str) -> u8 {
+    let mut counts = [0usize; CANDIDATE_DELIMITERS.len()];
+    let mut quoted = false;
+    for b in header.bytes() {
+        if b == b'"' {
+            quoted = !quoted;
+        } else if !quoted {
+            if let Some(i) = CANDIDATE_DELIMITERS.iter().position(|c| *c == b) {
+                counts[i] += 1;
+            }
+        }
+    }
+    let best = (0..counts.len()).max_by_key(|i| (counts[*i], usize::MAX - i)).unwrap();
+    if counts[best] > 0 { CANDIDATE_DELIMITERS[best] } else { b',' }
+}
+
+fn detect_quote(sample: &[String], delimiter: u8) -> u8 {
+    let starting_fields = |quote: char| sample.iter()
+        .flat_map(|line| line.split(delimiter as char))
+        .filter(|field| field.starts_with(quote))
+        .count();
+    if starting_fields('\'') > starting_fields('"') { b'\'' } else { b'"' }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delimiter_is_the_most_frequent_candidate() {
+        assert_eq!(detect_delimiter("name;city;zip"), b';');
+        assert_eq!(detect_delimiter("name\tcity,zip\tcountry"), b'\t');
+        assert_eq!(detect_delimiter("a|b|c"), b'|');
+    }
+
+    #[test]
+    fn delimiter_ignores_quoted_candidates() {
+        assert_eq!(detect_delimiter("\"last;first\",\"city;zip\",country"), b',');
+    }
+
+    #[test]
+    fn delimiter_defaults_to_comma() {
+        assert_eq!(detect_delimiter("name"), b',');
+        assert_eq!(detect_delimiter(""), b',');
+        assert_eq!(detect_delimiter("a;b,c"), b',');
+    }
+
+    #[test]
+    fn quote_is_the_character_starting_more_fields() {
+        let single = vec!["name;city".to_string(), "'Smith';'Köln'".to_string()];
+        assert_eq!(detect_quote(&single, b';'), b'\'');
+        let double = vec!["name,city".to_string(), "\"O'Brien\",Cork".to_string()];
+        assert_eq!(detect_quote(&double, b','), b'"');
+        assert_eq!(detect_quote(&[], b','), b'"');
+    }
+}
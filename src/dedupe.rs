@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::Hash;
+use std::io::Read;
+use std::path::Path;
+
+use fancy_regex::Regex;
+use glob::glob;
+use sha2::{Digest, Sha256};
+use zip::ZipArchive;
+
+use crate::cli::Cli;
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::OutputPrinter;
+
+/// Number of bytes hashed before hashing whole files, which rules out most files of equal size cheaply.
+const PARTIAL_HASH_BYTES: u64 = 4096;
+
+/// Extensions of archives whose entries are compared with --include-archives.
+const ARCHIVE_EXTENSIONS: [&str; 4] = ["zip", "jar", "war", "ear"];
+
+/// A file or, with --include-archives, an entry of an archive with its stored CRC32.
+enum Candidate {
+    File(String),
+    Entry { archive: String, name: String, crc32: u32 },
+}
+
+impl Candidate {
+    fn location(&self) -> String {
+        match self {
+            Candidate::File(path) => path.clone(),
+            Candidate::Entry { archive, name, .. } => format!("{}!/{}", archive, name),
+        }
+    }
+}
+
+pub(crate) fn process_dedupe(cli: &Cli, output: &mut dyn OutputPrinter) {
+    match &cli.search_expression {
+        Some(search_expression) => {
+            if needs_regex(cli) {
+                let re = build_regex(cli, search_expression, true);
+                process_dedupe_generic(cli, Some(&re), find_regex, output);
+            } else {
+                process_dedupe_generic(cli, Some(search_expression), find_simple, output);
+            }
+        }
+        None => {
+            process_dedupe_generic::<Regex>(cli, None, find_regex, output);
+        }
+    }
+}
+
+/// Prints every set of files with the same content as "checksum :: copies :: size :: wasted :: files", the sets
+/// wasting most space first, followed by the total. Files are grouped by size first, so files of a unique size are
+/// never read, and then by the hash of their start and the hash of their whole content. Archive entries are
+/// compared with each other and with files by CRC32, as that is what archives store.
+/// Empty files are ignored. Only paths matching the search expression are considered if there is one.
+fn process_dedupe_generic<T>(cli: &Cli, search_filter: Option<&T>,
+                             find_fn: fn(&str, &T) -> bool,
+                             output: &mut dyn OutputPrinter) {
+    let glob_pattern = &cli.glob_pattern;
+    let expected = format!("Failed to read glob pattern {}", glob_pattern);
+    let mut by_size: HashMap<u64, Vec<Candidate>> = HashMap::new();
+    for path in glob(glob_pattern).expect(&expected).flatten() {
+        let file_name = path.to_str().unwrap().to_string();
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str());
+                continue;
+            }
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        if cli.include_archives && is_archive(&path) {
+            if let Err(e) = collect_entries(&file_name, search_filter, find_fn, &mut by_size) {
+                output.err_output(format!("{} :: {}", file_name, e).as_str());
+            }
+        }
+        let selected = search_filter.map(|filter| find_fn(&file_name, filter)).unwrap_or(true);
+        if selected && metadata.len() > 0 {
+            by_size.entry(metadata.len()).or_default().push(Candidate::File(file_name));
+        }
+    }
+    let mut duplicate_sets = vec![];
+    for (size, candidates) in by_size.into_iter().filter(|(_, candidates)| candidates.len() > 1) {
+        let (entries, files): (Vec<Candidate>, Vec<Candidate>) = candidates.into_iter()
+            .partition(|candidate| matches!(candidate, Candidate::Entry { .. }));
+        let file_groups = group_by_content(size, files, output);
+        let groups = if entries.is_empty() {
+            file_groups
+        } else {
+            join_by_crc32(entries, file_groups, output)
+        };
+        duplicate_sets.extend(groups.into_iter()
+            .filter(|(_, candidates)| candidates.len() > 1)
+            .map(|(checksum, candidates)| (size, checksum, candidates)));
+    }
+    duplicate_sets.sort_by_key(|(size, checksum, candidates)| {
+        (std::cmp::Reverse(size * (candidates.len() as u64 - 1)), checksum.clone())
+    });
+    let mut total_wasted = 0;
+    for (size, checksum, candidates) in &duplicate_sets {
+        let wasted = size * (candidates.len() as u64 - 1);
+        total_wasted += wasted;
+        let locations = candidates.iter().map(|c| c.location()).collect::<Vec<String>>().join(", ");
+        output.output_with_stats(format!("{} :: {} copies :: {} bytes :: {} bytes wasted :: {}",
+                                         checksum, candidates.len(), size, wasted, locations).as_str());
+    }
+    output.output(format!("Wasted: {} bytes in {} duplicate sets", total_wasted, duplicate_sets.len()).as_str());
+}
+
+fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .map(|e| ARCHIVE_EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+fn collect_entries<T>(file_name: &str, search_filter: Option<&T>,
+                      find_fn: fn(&str, &T) -> bool,
+                      by_size: &mut HashMap<u64, Vec<Candidate>>) -> Result<(), String> {
+    let file = File::open(file_name).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i).map_err(|e| e.to_string())?;
+        let selected = search_filter.map(|filter| find_fn(entry.name(), filter)).unwrap_or(true);
+        if selected && !entry.is_dir() && entry.size() > 0 {
+            by_size.entry(entry.size()).or_default().push(Candidate::Entry {
+                archive: file_name.to_string(),
+                name: entry.name().to_string(),
+                crc32: entry.crc32(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Groups files of the given size with the same content, by the hash of their start and, if they are larger, by the
+/// hash of their whole content. Files without a copy are kept as groups of one, they may still match an archive entry.
+fn group_by_content(size: u64, files: Vec<Candidate>, output: &mut dyn OutputPrinter) -> Vec<(String, Vec<Candidate>)> {
+    let mut groups = vec![];
+    for (partial_hash, files) in group_by(files, |file| sha256_of(file, Some(PARTIAL_HASH_BYTES)), output) {
+        if size <= PARTIAL_HASH_BYTES || files.len() == 1 {
+            groups.push((format!("sha256 {}", partial_hash), files));
+        } else {
+            groups.extend(group_by(files, |file| sha256_of(file, None), output)
+                .into_iter()
+                .map(|(hash, files)| (format!("sha256 {}", hash), files)));
+        }
+    }
+    groups
+}
+
+/// Groups the archive entries by their CRC32 and adds every group of identical files to the entries with the CRC32
+/// of its content. Groups of files without such entries stay identified by their hash.
+fn join_by_crc32(entries: Vec<Candidate>, file_groups: Vec<(String, Vec<Candidate>)>,
+                 output: &mut dyn OutputPrinter) -> Vec<(String, Vec<Candidate>)> {
+    let mut by_crc32 = group_by(entries, crc32_of, output).into_iter().collect::<HashMap<u32, Vec<Candidate>>>();
+    let mut groups = vec![];
+    for (hash, files) in file_groups {
+        match crc32_of(&files[0]) {
+            Ok(crc32) => match by_crc32.get_mut(&crc32) {
+                Some(entries) => entries.extend(files),
+                None => groups.push((hash, files))
+            },
+            Err(e) => output.err_output(format!("Could not read {} due to {}", files[0].location(), e).as_str())
+        }
+    }
+    groups.extend(by_crc32.into_iter().map(|(crc32, candidates)| (format!("crc32 {:08x}", crc32), candidates)));
+    groups
+}
+
+/// Splits the candidates by key. Candidates whose key cannot be computed are reported as errors and dropped.
+fn group_by<K: Eq + Hash>(candidates: Vec<Candidate>, key_fn: impl Fn(&Candidate) -> std::io::Result<K>,
+                          output: &mut dyn OutputPrinter) -> Vec<(K, Vec<Candidate>)> {
+    let mut groups: HashMap<K, Vec<Candidate>> = HashMap::new();
+    for candidate in candidates {
+        match key_fn(&candidate) {
+            Ok(key) => groups.entry(key).or_default().push(candidate),
+            Err(e) => output.err_output(format!("Could not read {} due to {}", candidate.location(), e).as_str())
+        }
+    }
+    groups.into_iter().collect()
+}
+
+/// Hashes the whole file or only its first bytes.
+fn sha256_of(candidate: &Candidate, limit: Option<u64>) -> std::io::Result<String> {
+    let path = match candidate {
+        Candidate::File(path) => path,
+        Candidate::Entry { .. } => return Err(std::io::Error::other("archive entries are compared by CRC32")),
+    };
+    let file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    match limit {
+        Some(limit) => std::io::copy(&mut file.take(limit), &mut hasher)?,
+        None => std::io::copy(&mut std::io::BufReader::new(file), &mut hasher)?,
+    };
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// The stored CRC32 of archive entries or the computed CRC32 of files.
+fn crc32_of(candidate: &Candidate) -> std::io::Result<u32> {
+    match candidate {
+        Candidate::Entry { crc32, .. } => Ok(*crc32),
+        Candidate::File(path) => {
+            let mut file = std::io::BufReader::new(File::open(path)?);
+            let mut hasher = crc32fast::Hasher::new();
+            let mut buffer = [0u8; 65536];
+            loop {
+                let read = file.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+            Ok(hasher.finalize())
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+
+use fancy_regex::Regex;
+use glob::glob;
+use zip::ZipArchive;
+
+use crate::cli::Cli;
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::OutputPrinter;
+
+/// Entries which every jar has, so they are never reported as duplicates.
+const IGNORED_ENTRIES: [&str; 3] = ["META-INF/MANIFEST.MF", "META-INF/INDEX.LIST", "module-info.class"];
+
+/// An entry of an archive with its checksum and uncompressed size.
+struct Copy {
+    archive: String,
+    crc32: u32,
+    size: u64,
+}
+
+pub(crate) fn process_duplicates(cli: &Cli, output: &mut dyn OutputPrinter) {
+    match &cli.search_expression {
+        Some(search_expression) => {
+            if needs_regex(cli) {
+                let re = build_regex(cli, search_expression, true);
+                process_duplicates_generic(cli, Some(&re), find_regex, output);
+            } else {
+                process_duplicates_generic(cli, Some(search_expression), find_simple, output);
+            }
+        }
+        None => {
+            process_duplicates_generic::<Regex>(cli, None, find_regex, output);
+        }
+    }
+}
+
+/// Groups the entries of all archives matched by the glob pattern by path and prints every path found in more than
+/// one archive as "entry :: archive count :: archives". With --compare-content the groups are printed as
+/// "entry :: identical :: archives" or "entry :: conflict :: archive [crc, size], ..." instead.
+/// Only entries matching the search expression are considered if there is one.
+fn process_duplicates_generic<T>(cli: &Cli, search_filter: Option<&T>,
+                                 find_fn: fn(&str, &T) -> bool,
+                                 output: &mut dyn OutputPrinter) {
+    let glob_pattern = &cli.glob_pattern;
+    let expected = format!("Failed to read glob pattern {}", glob_pattern);
+    let mut entries: BTreeMap<String, Vec<Copy>> = BTreeMap::new();
+    for path in glob(glob_pattern).expect(&expected).flatten() {
+        let file_name = path.to_str().unwrap().to_string();
+        let archive = File::open(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|f| ZipArchive::new(f).map_err(|e| e.to_string()));
+        let mut archive = match archive {
+            Ok(archive) => archive,
+            Err(e) => {
+                output.err_output(format!("{} :: {}", file_name, e).as_str());
+                continue;
+            }
+        };
+        for i in 0..archive.len() {
+            let entry = match archive.by_index_raw(i) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    output.err_output(format!("{} :: {}", file_name, e).as_str());
+                    continue;
+                }
+            };
+            let name = entry.name();
+            if entry.is_dir() || IGNORED_ENTRIES.contains(&name) {
+                continue;
+            }
+            if let Some(filter) = search_filter {
+                if !find_fn(name, filter) {
+                    continue;
+                }
+            }
+            let copies = entries.entry(name.to_string()).or_default();
+            // Archives listing the same entry twice only count once
+            if copies.last().map(|c| c.archive != file_name).unwrap_or(true) {
+                copies.push(Copy { archive: file_name.clone(), crc32: entry.crc32(), size: entry.size() });
+            }
+        }
+    }
+    for (name, copies) in entries.iter().filter(|(_, copies)| copies.len() > 1) {
+        let archives = copies.iter().map(|c| c.archive.as_str()).collect::<Vec<&str>>().join(", ");
+        if !cli.compare_content {
+            output.output_with_stats(format!("{} :: {} archives :: {}", name, copies.len(), archives).as_str());
+        } else if copies.iter().all(|c| c.crc32 == copies[0].crc32 && c.size == copies[0].size) {
+            output.output_with_stats(format!("{} :: identical :: {}", name, archives).as_str());
+        } else {
+            let described = copies.iter()
+                .map(|c| format!("{} [crc {:08x}, {} bytes]", c.archive, c.crc32, c.size))
+                .collect::<Vec<String>>()
+                .join(", ");
+            output.output_with_stats(format!("{} :: conflict :: {}", name, described).as_str());
+        }
+    }
+}
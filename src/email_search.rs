@@ -0,0 +1,205 @@
+use std::fs::{self, File};
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use mail_parser::mailbox::mbox::MessageIterator;
+use mail_parser::{Address, Addr, HeaderValue, Message, MessageParser, MessagePart, MimeHeaders, PartType};
+
+use crate::cli::{Cli, EmailPart};
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::pdf_search::extract_pdf_pages_from_bytes;
+use crate::text_cache::TextCache;
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+pub(crate) fn process_email_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    let search_expression = cli.search_expression.as_ref().unwrap();
+    if needs_regex(cli) {
+        let re = build_regex(cli, search_expression, true);
+        process_email_search_generic(path, cli, &re, find_regex, output);
+    } else {
+        process_email_search_generic(path, cli, search_expression, find_simple, output);
+    }
+}
+
+/// The search state shared by all messages of a file.
+struct EmailSearch<'a, T> {
+    search_filter: &'a T,
+    find_fn: fn(&str, &T) -> bool,
+    part: Option<EmailPart>,
+    attachments: bool,
+    cache: Option<TextCache>,
+}
+
+/// Searches single messages (.eml) and mbox files. Messages of mbox files are numbered from 1.
+fn process_email_search_generic<T>(path: PathBuf, cli: &Cli, search_filter: &T,
+                                   find_fn: fn(&str, &T) -> bool,
+                                   output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().unwrap();
+    let search = EmailSearch {
+        search_filter,
+        find_fn,
+        part: cli.email_part.clone(),
+        attachments: cli.attachments,
+        cache: TextCache::from_cli(cli),
+    };
+    match is_mbox(&path) {
+        Ok(true) => {
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) => {
+                    output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str());
+                    return;
+                }
+            };
+            for (index, entry) in MessageIterator::new(file).enumerate() {
+                let location = format!("{} :: message {}", file_name, index + 1);
+                match entry {
+                    Ok(entry) => search.parse_and_search(&location, entry.contents(), output),
+                    Err(_) => output.err_output(format!("{} :: Could not read message", location).as_str())
+                }
+            }
+        }
+        Ok(false) => match fs::read(&path) {
+            Ok(content) => search.parse_and_search(file_name, &content, output),
+            Err(e) => output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str())
+        },
+        Err(e) => output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str())
+    }
+}
+
+/// Files with the mbox extension or starting with the "From " separator line are mailboxes.
+fn is_mbox(path: &Path) -> std::io::Result<bool> {
+    if path.extension().map(|e| e.to_string_lossy().to_lowercase() == "mbox").unwrap_or(false) {
+        return Ok(true);
+    }
+    let mut start = [0u8; 5];
+    let read = File::open(path)?.read(&mut start)?;
+    Ok(&start[..read] == b"From ")
+}
+
+impl<T> EmailSearch<'_, T> {
+    fn parse_and_search(&self, location: &str, content: &[u8], output: &mut dyn OutputPrinter) {
+        match MessageParser::default().parse(content) {
+            Some(message) => self.search_message(location, &message, output),
+            None => output.err_output(format!("{} :: Could not parse message", location).as_str())
+        }
+    }
+
+    /// Prints "location :: header :: name :: value" for matching headers and "location :: body :: line :: text"
+    /// for matching lines of the text bodies, html bodies being converted to text.
+    fn search_message(&self, location: &str, message: &Message, output: &mut dyn OutputPrinter) {
+        if !matches!(self.part, Some(EmailPart::Body)) {
+            for header in message.headers() {
+                let value = header_text(header.value())
+                    .or_else(|| message.header_raw(header.name()).map(|raw| raw.trim().to_string()));
+                if let Some(value) = value {
+                    if (self.find_fn)(&value, self.search_filter) {
+                        let fields = HitFields { matched: Some(value.clone()), ..HitFields::default() };
+                        output.output_hit(format!("{} :: header :: {} :: {}", location, header.name(), value).as_str(),
+                                          fields);
+                    }
+                }
+            }
+        }
+        if matches!(self.part, Some(EmailPart::Headers)) {
+            return;
+        }
+        for index in 0..message.text_body_count() {
+            if let Some(body) = message.body_text(index) {
+                self.search_lines(&format!("{} :: body", location), &body, output);
+            }
+        }
+        if self.attachments {
+            for attachment in message.attachments() {
+                self.search_attachment(location, attachment, output);
+            }
+        }
+    }
+
+    fn search_lines(&self, location: &str, text: &str, output: &mut dyn OutputPrinter) {
+        for (linenumber, line) in text.lines().enumerate() {
+            if (self.find_fn)(line, self.search_filter) {
+                let fields = HitFields {
+                    line: Some(linenumber as u64 + 1),
+                    matched: Some(line.trim().to_string()),
+                    ..HitFields::default()
+                };
+                output.output_hit(format!("{} :: {} :: {}", location, linenumber, line.trim()).as_str(), fields);
+            }
+        }
+    }
+
+    /// Text attachments are searched line by line, pdf attachments page by page and zip attachments by entry name.
+    /// Attached messages are searched like the message itself.
+    fn search_attachment(&self, location: &str, attachment: &MessagePart, output: &mut dyn OutputPrinter) {
+        let name = attachment.attachment_name().unwrap_or("unnamed");
+        let attachment_location = format!("{} :: attachment {}", location, name);
+        let lower_name = name.to_lowercase();
+        match &attachment.body {
+            PartType::Message(message) => self.search_message(&attachment_location, message, output),
+            PartType::Text(text) | PartType::Html(text) => self.search_lines(&attachment_location, text, output),
+            PartType::Binary(bytes) | PartType::InlineBinary(bytes) => {
+                if lower_name.ends_with(".pdf") {
+                    let extracted = match &self.cache {
+                        Some(cache) => cache.get_or_extract_bytes(bytes, extract_pdf_pages_from_bytes, output),
+                        None => extract_pdf_pages_from_bytes(bytes)
+                    };
+                    match extracted {
+                        Ok(pages) => {
+                            for (index, page) in pages.iter().enumerate() {
+                                self.search_lines(&format!("{} :: page {}", attachment_location, index + 1), page, output);
+                            }
+                        }
+                        Err(e) => output.err_output(format!("{} :: {}", attachment_location, e).as_str())
+                    }
+                } else if lower_name.ends_with(".zip") || lower_name.ends_with(".jar") {
+                    match zip::ZipArchive::new(Cursor::new(bytes.as_ref())) {
+                        Ok(archive) => {
+                            for entry_name in archive.file_names() {
+                                if (self.find_fn)(entry_name, self.search_filter) {
+                                    let fields = HitFields {
+                                        entry: Some(entry_name.to_string()),
+                                        matched: Some(entry_name.to_string()),
+                                        ..HitFields::default()
+                                    };
+                                    output.output_hit(format!("{} :: {}", attachment_location, entry_name).as_str(),
+                                                      fields);
+                                }
+                            }
+                        }
+                        Err(e) => output.err_output(format!("{} :: {:?}", attachment_location, e).as_str())
+                    }
+                }
+            }
+            PartType::Multipart(_) => {}
+        }
+    }
+}
+
+/// Decoded text of the header, or None for headers which are better shown raw, like Received.
+fn header_text(value: &HeaderValue) -> Option<String> {
+    match value {
+        HeaderValue::Text(text) => Some(text.to_string()),
+        HeaderValue::TextList(texts) => Some(texts.join(", ")),
+        HeaderValue::Address(Address::List(addresses)) => Some(format_addresses(addresses)),
+        HeaderValue::Address(Address::Group(groups)) => Some(groups.iter()
+            .map(|group| format!("{}: {}", group.name.as_deref().unwrap_or_default(), format_addresses(&group.addresses)))
+            .collect::<Vec<String>>()
+            .join("; ")),
+        HeaderValue::DateTime(date) => Some(date.to_rfc822()),
+        _ => None
+    }
+}
+
+fn format_addresses(addresses: &[Addr]) -> String {
+    addresses.iter()
+        .map(|addr| match (&addr.name, &addr.address) {
+            (Some(name), Some(address)) => format!("{} <{}>", name, address),
+            (Some(name), None) => name.to_string(),
+            (None, Some(address)) => address.to_string(),
+            (None, None) => String::new()
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
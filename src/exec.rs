@@ -0,0 +1,269 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::process::{self, Child, Command, Stdio};
+
+use crate::cli::{Cli, Mode};
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+/// Placeholders which make --exec run once per hit instead of once per matching file.
+const HIT_PLACEHOLDERS: [&str; 4] = ["{entry}", "{line}", "{offset}", "{match}"];
+
+/// Forwards everything to the wrapped printer and keeps the parts of the hits, so commands can be run for them.
+pub(crate) struct HitCollector<'a> {
+    pub(crate) inner: &'a mut dyn OutputPrinter,
+    pub(crate) hits: Vec<HitFields>,
+}
+
+impl OutputPrinter for HitCollector<'_> {
+    fn print_param_map(&mut self, map: HashMap<String, String>) {
+        self.inner.print_param_map(map);
+    }
+
+    fn output_with_stats(&mut self, msg: &str) {
+        self.inner.output_with_stats(msg);
+        self.hits.push(HitFields::default());
+    }
+
+    fn output_hit(&mut self, msg: &str, fields: HitFields) {
+        self.inner.output_hit(msg, fields.clone());
+        self.hits.push(fields);
+    }
+
+    fn output(&mut self, msg: &str) {
+        self.inner.output(msg);
+    }
+
+    fn err_output(&mut self, msg: &str) {
+        self.inner.err_output(msg);
+    }
+
+    fn get_name(&self) -> &str {
+        self.inner.get_name()
+    }
+
+    fn print_stats(&mut self) {
+        self.inner.print_stats();
+    }
+}
+
+/// Runs the --exec command for the matching files or their hits and the --exec-batch command once for all
+/// matching files. Up to --exec-jobs commands run at the same time. Commands which cannot be started or
+/// exit with a non-zero status are counted as errors.
+pub(crate) struct Executor {
+    command: Option<Vec<String>>,
+    batch_command: Option<Vec<String>>,
+    jobs: usize,
+    running: VecDeque<(Child, String)>,
+    batch_paths: Vec<String>,
+    seen_paths: HashSet<String>,
+}
+
+impl Executor {
+    /// Returns None if neither --exec nor --exec-batch is given. Exits if a command is invalid
+    /// or the mode does not search file by file.
+    pub(crate) fn from_cli(cli: &Cli) -> Option<Executor> {
+        if cli.exec.is_none() && cli.exec_batch.is_none() {
+            return None;
+        }
+        if matches!(cli.mode, Mode::Duplicates | Mode::Dedupe) {
+            eprintln!("--exec and --exec-batch are not supported in duplicates and dedupe mode");
+            process::exit(0x0001);
+        }
+        let parse = |command: &String| match split_command(command) {
+            Ok(args) => args,
+            Err(e) => {
+                eprintln!("Invalid command '{}': {}", command, e);
+                process::exit(0x0001);
+            }
+        };
+        Some(Executor {
+            command: cli.exec.as_ref().map(parse),
+            batch_command: cli.exec_batch.as_ref().map(parse),
+            jobs: cli.exec_jobs.unwrap_or(1).max(1),
+            running: VecDeque::new(),
+            batch_paths: vec![],
+            seen_paths: HashSet::new(),
+        })
+    }
+
+    /// Runs the command for the hits of a processed file. Files without hits are ignored.
+    pub(crate) fn file_processed(&mut self, path: &str, hits: &[HitFields], output: &mut dyn OutputPrinter) {
+        if hits.is_empty() {
+            return;
+        }
+        if self.batch_command.is_some() && self.seen_paths.insert(path.to_string()) {
+            self.batch_paths.push(path.to_string());
+        }
+        let command = match &self.command {
+            Some(command) => command.clone(),
+            None => return
+        };
+        let per_hit = command.iter().any(|arg| HIT_PLACEHOLDERS.iter().any(|p| arg.contains(p)));
+        if per_hit {
+            // Hits of which nothing is known would all run the same command, so it only runs once for them
+            let mut ran_without_fields = false;
+            for fields in hits {
+                if fields.is_empty() {
+                    if ran_without_fields {
+                        continue;
+                    }
+                    ran_without_fields = true;
+                }
+                let args = command.iter()
+                    .map(|arg| replace_placeholders(arg, path, fields))
+                    .collect::<Vec<String>>();
+                self.spawn(args, output);
+            }
+        } else {
+            let args = command.iter()
+                .map(|arg| replace_placeholders(arg, path, &HitFields::default()))
+                .collect::<Vec<String>>();
+            self.spawn(args, output);
+        }
+    }
+
+    /// Waits for the running commands and runs the batch command with all matching files.
+    pub(crate) fn finish(&mut self, output: &mut dyn OutputPrinter) {
+        if let Some(batch_command) = self.batch_command.clone() {
+            if !self.batch_paths.is_empty() {
+                let mut args = vec![];
+                let mut has_placeholder = false;
+                for arg in &batch_command {
+                    if arg == "{}" || arg == "{path}" {
+                        args.extend(self.batch_paths.iter().cloned());
+                        has_placeholder = true;
+                    } else {
+                        args.push(arg.clone());
+                    }
+                }
+                // Like "find -exec ... +", the paths go last if there is no placeholder
+                if !has_placeholder {
+                    args.extend(self.batch_paths.iter().cloned());
+                }
+                self.spawn(args, output);
+            }
+        }
+        while !self.running.is_empty() {
+            self.wait_oldest(output);
+        }
+    }
+
+    fn spawn(&mut self, args: Vec<String>, output: &mut dyn OutputPrinter) {
+        while self.running.len() >= self.jobs {
+            self.wait_oldest(output);
+        }
+        let description = args.join(" ");
+        let mut command = Command::new(&args[0]);
+        command.args(&args[1..]);
+        // The hits are printed to stdout, so the output of the commands goes to stderr to keep them apart
+        if output.get_name().eq("StdPrinter") {
+            command.stdout(Stdio::from(io::stderr()));
+        }
+        match command.spawn() {
+            Ok(child) => self.running.push_back((child, description)),
+            Err(e) => output.err_output(format!("Could not run {} :: {}", description, e).as_str())
+        }
+    }
+
+    fn wait_oldest(&mut self, output: &mut dyn OutputPrinter) {
+        if let Some((mut child, description)) = self.running.pop_front() {
+            match child.wait() {
+                Ok(status) if status.success() => {}
+                Ok(status) => output.err_output(format!("{} :: {}", description, status).as_str()),
+                Err(e) => output.err_output(format!("Could not wait for {} :: {}", description, e).as_str())
+            }
+        }
+    }
+}
+
+fn replace_placeholders(arg: &str, path: &str, fields: &HitFields) -> String {
+    arg.replace("{path}", path)
+        .replace("{}", path)
+        .replace("{entry}", fields.entry.as_deref().unwrap_or_default())
+        .replace("{line}", &fields.line.map(|line| line.to_string()).unwrap_or_default())
+        .replace("{offset}", &fields.offset.map(|offset| offset.to_string()).unwrap_or_default())
+        .replace("{match}", fields.matched.as_deref().unwrap_or_default())
+}
+
+/// Splits the command into arguments at whitespace outside of single or double quotes.
+/// Backslashes are kept as they are, as they separate the directories of Windows paths.
+fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+    for c in command.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err("unbalanced quote".to_string());
+    }
+    if in_arg {
+        args.push(current);
+    }
+    if args.is_empty() {
+        return Err("the command is empty".to_string());
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_at_whitespace_outside_of_quotes() {
+        assert_eq!(split_command("gzip  -k {}"), Ok(vec!["gzip".to_string(), "-k".to_string(), "{}".to_string()]));
+        assert_eq!(split_command(r#"echo "a b" 'c "d"' e''"#),
+                   Ok(vec!["echo".to_string(), "a b".to_string(), "c \"d\"".to_string(), "e".to_string()]));
+        assert_eq!(split_command("''"), Ok(vec!["".to_string()]));
+    }
+
+    #[test]
+    fn keeps_backslashes() {
+        assert_eq!(split_command(r"copy C:\logs\{} D:\"), Ok(vec!["copy".to_string(), r"C:\logs\{}".to_string(),
+                                                                   r"D:\".to_string()]));
+    }
+
+    #[test]
+    fn rejects_invalid_commands() {
+        assert_eq!(split_command("echo \"a"), Err("unbalanced quote".to_string()));
+        assert_eq!(split_command("  "), Err("the command is empty".to_string()));
+    }
+
+    #[test]
+    fn replaces_placeholders() {
+        let fields = HitFields {
+            entry: Some("a/b.txt".to_string()),
+            line: Some(3),
+            offset: Some(16),
+            matched: Some("foo".to_string()),
+        };
+        assert_eq!(replace_placeholders("{}:{path}:{entry}:{line}:{offset}:{match}", "x.zip", &fields),
+                   "x.zip:x.zip:a/b.txt:3:16:foo");
+    }
+
+    #[test]
+    fn unknown_hit_fields_are_empty() {
+        assert_eq!(replace_placeholders("[{entry}][{line}][{offset}][{match}]", "x", &HitFields::default()),
+                   "[][][][]");
+    }
+}
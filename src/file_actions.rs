@@ -0,0 +1,272 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::cli::{Cli, ConflictStrategy, Mode};
+use crate::io_ops::{glob_base, resolve_path, target_path};
+use crate::OutputPrinter;
+
+/// What happens to the files with hits.
+enum Action {
+    Copy(PathBuf),
+    Move(PathBuf),
+    Delete,
+}
+
+impl Action {
+    fn verb(&self) -> &'static str {
+        match self {
+            Action::Copy(_) => "copy",
+            Action::Move(_) => "move",
+            Action::Delete => "delete",
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            Action::Copy(_) => "Copied",
+            Action::Move(_) => "Moved",
+            Action::Delete => "Deleted",
+        }
+    }
+}
+
+/// A file with hits and where it goes. Files without a target are deleted or skipped for the given reason.
+struct Step {
+    source: PathBuf,
+    target: Option<PathBuf>,
+    skipped: Option<&'static str>,
+    size: u64,
+}
+
+/// Copies, moves or deletes the files with hits after searching. The planned steps are always listed first and only
+/// applied with --yes or after confirming on the terminal. The directories are resolved, so files are compared by
+/// their real location.
+pub(crate) struct FileActions {
+    action: Action,
+    base: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl FileActions {
+    /// Returns None if none of --copy-to, --move-to and --delete is given. Exits if more than one is given,
+    /// the mode does not search file by file or the target directory contains the searched files.
+    pub(crate) fn from_cli(cli: &Cli) -> Option<FileActions> {
+        let mut actions = vec![];
+        if let Some(dir) = &cli.copy_to {
+            actions.push(Action::Copy(PathBuf::from(dir)));
+        }
+        if let Some(dir) = &cli.move_to {
+            actions.push(Action::Move(PathBuf::from(dir)));
+        }
+        if cli.delete {
+            actions.push(Action::Delete);
+        }
+        if actions.len() > 1 {
+            eprintln!("Please use only one of --copy-to, --move-to and --delete");
+            process::exit(0x0001);
+        }
+        let action = actions.pop()?;
+        if matches!(cli.mode, Mode::Duplicates | Mode::Dedupe) {
+            eprintln!("--copy-to, --move-to and --delete are not supported in duplicates and dedupe mode");
+            process::exit(0x0001);
+        }
+        let base = resolve_or_exit(&glob_base(&cli.glob_pattern));
+        let action = match action {
+            Action::Copy(dir) => Action::Copy(resolve_target_dir(&dir, &base)),
+            Action::Move(dir) => Action::Move(resolve_target_dir(&dir, &base)),
+            Action::Delete => Action::Delete,
+        };
+        Some(FileActions { action, base, files: vec![] })
+    }
+
+    pub(crate) fn add(&mut self, path: PathBuf) {
+        if !self.files.contains(&path) {
+            self.files.push(path);
+        }
+    }
+
+    /// Lists the steps as "action :: file :: target :: size" and applies them if confirmed, followed by the summary.
+    pub(crate) fn apply(&self, cli: &Cli, output: &mut dyn OutputPrinter) {
+        let strategy = cli.on_conflict.as_ref().unwrap_or(&ConflictStrategy::Skip);
+        let steps = match self.plan(strategy) {
+            Ok(steps) => steps,
+            Err(e) => {
+                output.err_output(e.as_str());
+                output.output("Nothing was changed");
+                return;
+            }
+        };
+        let verb = self.action.verb();
+        let lines = steps.iter()
+            .map(|step| {
+                let target = match (&step.target, step.skipped) {
+                    (Some(target), _) => target.display().to_string(),
+                    (None, Some(reason)) => reason.to_string(),
+                    (None, None) => "".to_string(),
+                };
+                format!("{} :: {} :: {} :: {} bytes", verb, step.source.display(), target, step.size)
+            })
+            .collect::<Vec<String>>();
+        for line in &lines {
+            output.output(line);
+        }
+        let selected = steps.iter()
+            .filter(|step| step.skipped.is_none())
+            .collect::<Vec<&Step>>();
+        let bytes: u64 = selected.iter().map(|step| step.size).sum();
+        if selected.is_empty() {
+            output.output(format!("Nothing to {}", verb).as_str());
+            return;
+        }
+        // Only the console printer shows the steps on the terminal before the question
+        let shown = output.get_name() == "StdPrinter";
+        if !cli.yes && !confirm(verb, selected.len(), bytes, if shown { &[] } else { &lines }) {
+            output.output(format!("Dry run: would {} {} files, {} bytes. Use --yes to apply", verb, selected.len(),
+                                  bytes).as_str());
+            return;
+        }
+        let (mut files, mut applied_bytes) = (0, 0);
+        for step in selected {
+            let result = match (&self.action, &step.target) {
+                (Action::Copy(_), Some(target)) => copy_file(&step.source, target),
+                (Action::Move(_), Some(target)) => move_file(&step.source, target),
+                _ => fs::remove_file(&step.source),
+            };
+            match result {
+                Ok(()) => {
+                    files += 1;
+                    applied_bytes += step.size;
+                }
+                Err(e) => output.err_output(format!("Could not {} {} :: {}", verb, step.source.display(), e).as_str())
+            }
+        }
+        output.output(format!("{}: {} files, {} bytes", self.action.past_tense(), files, applied_bytes).as_str());
+    }
+
+    /// Determines the target of every file, resolving conflicts with existing files by the strategy.
+    fn plan(&self, strategy: &ConflictStrategy) -> Result<Vec<Step>, String> {
+        // The files with hits are never replaced, neither are targets of other files
+        let mut planned = self.files.iter().flat_map(|file| resolve_path(file)).collect::<HashSet<PathBuf>>();
+        let mut steps = vec![];
+        for source in &self.files {
+            let size = fs::metadata(source).map(|m| m.len()).map_err(|e| format!("{} :: {}", source.display(), e))?;
+            let dir = match &self.action {
+                Action::Copy(dir) | Action::Move(dir) => dir,
+                Action::Delete => {
+                    steps.push(Step { source: source.clone(), target: None, skipped: None, size });
+                    continue;
+                }
+            };
+            let skip = |reason| Step { source: source.clone(), target: None, skipped: Some(reason), size };
+            let resolved_source = resolve_path(source).map_err(|e| format!("{} :: {}", source.display(), e))?;
+            // Files already below the target directory stay where they are
+            if resolved_source.starts_with(dir) {
+                steps.push(skip("skipped, inside target"));
+                continue;
+            }
+            let mut target = target_path(dir, &self.base, &resolved_source);
+            if resolve_path(&target).map(|t| t == resolved_source).unwrap_or(true) {
+                steps.push(skip("skipped, same file"));
+                continue;
+            }
+            let exists = |target: &Path, planned: &HashSet<PathBuf>| target.exists() || planned.contains(target);
+            if exists(&target, &planned) {
+                match strategy {
+                    ConflictStrategy::Skip => {
+                        steps.push(skip("skipped, exists"));
+                        continue;
+                    }
+                    ConflictStrategy::Overwrite if planned.contains(&target) => {
+                        return Err(format!("Cannot overwrite {}, it has hits or is the target of another file",
+                                           target.display()));
+                    }
+                    ConflictStrategy::Overwrite => {}
+                    ConflictStrategy::Rename => {
+                        let original = target.clone();
+                        let mut counter = 1;
+                        while exists(&target, &planned) {
+                            target = numbered_path(&original, counter);
+                            counter += 1;
+                        }
+                    }
+                    ConflictStrategy::Fail => {
+                        return Err(format!("{} exists already", target.display()));
+                    }
+                }
+            }
+            planned.insert(target.clone());
+            steps.push(Step { source: source.clone(), target: Some(target), skipped: None, size });
+        }
+        Ok(steps)
+    }
+}
+
+fn resolve_or_exit(path: &Path) -> PathBuf {
+    match resolve_path(path) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            eprintln!("Invalid path {}: {}", path.display(), e);
+            process::exit(0x0001);
+        }
+    }
+}
+
+/// Exits if the target directory contains the directory of the glob pattern, as the files would be copied or
+/// moved onto themselves.
+fn resolve_target_dir(dir: &Path, base: &Path) -> PathBuf {
+    let resolved = resolve_or_exit(dir);
+    if base.starts_with(&resolved) {
+        eprintln!("The target directory {} contains the searched files, please use another one", dir.display());
+        process::exit(0x0001);
+    }
+    resolved
+}
+
+/// Adds a counter to the file name, e.g. report_1.txt.
+fn numbered_path(path: &Path, counter: usize) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, counter, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, counter),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Asks on the terminal, writing to stderr so that the question never ends up in the results. The steps are listed
+/// first if the printer does not show them on the terminal. Without a terminal nothing is applied.
+fn confirm(verb: &str, files: usize, bytes: u64, steps: &[String]) -> bool {
+    if !io::stdin().is_terminal() {
+        return false;
+    }
+    for step in steps {
+        eprintln!("{}", step);
+    }
+    eprint!("{} {} files, {} bytes? [y/N] ", verb, files, bytes);
+    let _ = io::stderr().flush();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn copy_file(source: &Path, target: &Path) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(source, target).map(|_| ())
+}
+
+/// Renames the file, falling back to copying and deleting it when the target is on another file system.
+fn move_file(source: &Path, target: &Path) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(source, target) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(source, target)?;
+            fs::remove_file(source)
+        }
+        result => result
+    }
+}
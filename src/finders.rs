@@ -1,9 +1,45 @@
+use fancy_regex::Regex;
 
+use crate::cli::Cli;
 
 pub(crate) fn find_simple(content: &str, search_filter: &String) -> bool {
-    content.find(search_filter).is_some()
+    content.contains(search_filter)
 }
 
-pub(crate) fn find_simple_pos(content: &str, search_filter: &String) -> Option<usize> {
-    content.find(search_filter)
-}
\ No newline at end of file
+pub(crate) fn find_simple_pos(content: &str, search_filter: &String) -> Option<(usize, usize)> {
+    content.find(search_filter).map(|start| (start, start + search_filter.len()))
+}
+
+pub(crate) fn find_regex(content: &str, search_filter: &Regex) -> bool {
+    matches!(search_filter.find(content), Ok(Some(_)))
+}
+
+pub(crate) fn find_regex_pos(content: &str, search_filter: &Regex) -> Option<(usize, usize)> {
+    match search_filter.find(content) {
+        Ok(Some(m)) => Some((m.start(), m.end())),
+        _ => None
+    }
+}
+
+/// Returns true if a literal search has to be executed as a regular expression,
+/// which is the case when case-insensitive or whole word matching was requested.
+pub(crate) fn needs_regex(cli: &Cli) -> bool {
+    cli.ignore_case || cli.word
+}
+
+/// Builds the regular expression for the search expression, applying the matcher options of the command line.
+/// Literal expressions are escaped before the options are applied.
+pub(crate) fn build_regex(cli: &Cli, search_expression: &str, literal: bool) -> Regex {
+    let mut pattern = if literal {
+        fancy_regex::escape(search_expression).to_string()
+    } else {
+        search_expression.to_string()
+    };
+    if cli.word {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+    if cli.ignore_case {
+        pattern = format!("(?i){}", pattern);
+    }
+    Regex::new(&pattern).expect("Invalid regex")
+}
@@ -20,6 +20,14 @@ impl HtmlPrinter<'_> {
     }
 }
 
+/// Escapes the characters with a meaning in HTML, so content of the searched files is shown as it is.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 macro_rules! td_format {() => ("<tr><td>{}</td><td>{}</td></tr>")}
 
 macro_rules! simple_td_format {() => ("<td>{}</td>")}
@@ -35,7 +43,7 @@ impl OutputPrinter for HtmlPrinter<'_> {
     fn print_param_map(&mut self, map: HashMap<String, String>) {
         let mut table_content = "".to_string();
         for (key, value) in &map {
-            let string = format!(td_format!(), key, value);
+            let string = format!(td_format!(), escape_html(key), escape_html(value));
             table_content += &string;
         }
         let msg = format!(r###"
@@ -95,7 +103,7 @@ impl OutputPrinter for HtmlPrinter<'_> {
         let splits = msg.split("::");
         let mut acc = Self::start_row();
         for s in splits {
-            acc += format!(simple_td_format!(), s).as_str()
+            acc += format!(simple_td_format!(), escape_html(s)).as_str()
         }
         acc += "</tr>";
         self.print_to_file(acc.as_str(), "message");
@@ -103,7 +111,7 @@ impl OutputPrinter for HtmlPrinter<'_> {
 
     fn err_output(&mut self, msg: &str) {
         let mut acc = Self::start_row();
-        acc += format!(simple_td_format!(), msg).as_str();
+        acc += format!(simple_td_format!(), escape_html(msg)).as_str();
         acc += "</tr>";
         self.statistics.increase_errors();
     }
@@ -1,13 +1,101 @@
 use std::fs::File;
 use std::io::{self, BufRead};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
 #[cfg(windows)]
-pub const LINE_ENDING: &'static str = "\r\n";
+pub const LINE_ENDING: &str = "\r\n";
 #[cfg(not(windows))]
-pub const LINE_ENDING: &'static str = "\n";
+pub const LINE_ENDING: &str = "\n";
 
 pub(crate) fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>> where P: AsRef<Path>, {
     let file = File::open(filename)?;
     Ok(io::BufReader::new(file).lines())
-}
\ No newline at end of file
+}
+
+/// The directory of the glob pattern before the first component with a wildcard.
+pub(crate) fn glob_base(glob_pattern: &str) -> PathBuf {
+    Path::new(glob_pattern).components()
+        .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect::<PathBuf>()
+}
+
+/// The absolute path with symbolic links resolved, also for paths which do not exist yet. The components are
+/// resolved one by one: existing paths are canonicalized and `..` goes to the parent of the path resolved so far.
+pub(crate) fn resolve_path(path: &Path) -> io::Result<PathBuf> {
+    let mut resolved = if path.is_absolute() { PathBuf::new() } else { std::env::current_dir()?.canonicalize()? };
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => resolved.push(component),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(name) => {
+                resolved.push(name);
+                if let Ok(canonical) = resolved.canonicalize() {
+                    resolved = canonical;
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+/// The path below the target directory, relative to the base of the glob pattern, so files never share a target.
+/// Both the base and the source are resolved paths. Sources outside of the base, reached through symbolic links,
+/// keep their whole path without the root.
+pub(crate) fn target_path(dir: &Path, base: &Path, source: &Path) -> PathBuf {
+    let relative = match source.strip_prefix(base) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative.to_path_buf(),
+        // The glob pattern is the file itself
+        Ok(_) => source.file_name().map(PathBuf::from).unwrap_or_default(),
+        Err(_) => source.components().filter(|c| matches!(c, Component::Normal(_))).collect(),
+    };
+    dir.join(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn glob_base_stops_at_the_first_wildcard() {
+        assert_eq!(glob_base("/data/exports/**/*.csv"), PathBuf::from("/data/exports"));
+        assert_eq!(glob_base("data/tb_[ab].csv"), PathBuf::from("data"));
+        assert_eq!(glob_base("*.csv"), PathBuf::new());
+        assert_eq!(glob_base("/data/file.csv"), PathBuf::from("/data/file.csv"));
+    }
+
+    #[test]
+    fn target_path_keeps_the_path_below_the_base() {
+        let dir = Path::new("/target");
+        assert_eq!(target_path(dir, Path::new("/data"), Path::new("/data/a/b.csv")), PathBuf::from("/target/a/b.csv"));
+        assert_eq!(target_path(dir, Path::new("/data/b.csv"), Path::new("/data/b.csv")), PathBuf::from("/target/b.csv"));
+        assert_eq!(target_path(dir, Path::new("/data"), Path::new("/other/b.csv")),
+                   PathBuf::from("/target/other/b.csv"));
+    }
+
+    #[test]
+    fn resolve_path_appends_the_missing_components() {
+        let dir = std::env::temp_dir().join(format!("file_search_resolve_{}", std::process::id()));
+        fs::create_dir_all(dir.join("existing")).unwrap();
+        let resolved = resolve_path(&dir.join("existing").join("..").join("missing").join("file.txt"));
+        let canonical = dir.canonicalize().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(resolved.unwrap(), canonical.join("missing").join("file.txt"));
+    }
+
+    #[test]
+    fn resolve_path_goes_up_for_parent_components() {
+        let dir = std::env::temp_dir().join(format!("file_search_resolve_parent_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let canonical = dir.canonicalize().unwrap();
+        let parent = resolve_path(&dir.join(".."));
+        let missing_parent = resolve_path(&dir.join("missing").join("sub").join(".."));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(parent.unwrap(), canonical.parent().unwrap());
+        assert_eq!(missing_parent.unwrap(), canonical.join("missing"));
+    }
+}
@@ -1,26 +1,391 @@
-use std::fs;
-use std::path::PathBuf;
-use jsonpath_rust::JsonPathFinder;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use jsonpath_rust::{JsonPathFinder, JsonPathInst};
 
+use fancy_regex::Regex;
+use flate2::read::GzDecoder;
+use serde::Deserialize;
 use serde_json::Value;
 
+use crate::cli::Cli;
+use crate::finders::build_regex;
+use crate::io_ops::read_lines;
+use crate::result_printer::HitFields;
 use crate::OutputPrinter;
 
-pub(crate) fn process_file_with_json_path(path: PathBuf, search_expression_option: &Option<String>,
-                                          output: &mut dyn OutputPrinter) {
-    let path_clone = path.clone();
-    let json_path_str = search_expression_option.as_ref().unwrap();
-    let json_string_res = fs::read_to_string(path_clone);
-    match json_string_res {
-        Ok(json_string) => {
-            let finder = JsonPathFinder::from_str(&json_string, json_path_str).unwrap();
-            let slice_of_data: Vec<&Value> = finder.find_slice();
-            if !slice_of_data.is_empty() {
-                output.output_with_stats(format!("{} :: {:?}", path.to_str().unwrap(), slice_of_data).as_str());
+/// Extensions of archive entries which are parsed as documents.
+const JSON_EXTENSIONS: [&str; 4] = ["json", "yaml", "yml", "toml"];
+
+/// Extensions of files with one json document per line.
+const JSON_LINES_EXTENSIONS: [&str; 2] = ["ndjson", "jsonl"];
+
+/// The json path and the value filter of the command line, parsed once for all files.
+pub(crate) struct JsonPathMatcher {
+    // The finder owns the parsed path, the documents are set one after the other
+    finder: RefCell<JsonPathFinder>,
+    value_filter: Option<ValueFilter>,
+}
+
+/// Parses the json path of the search expression and the value filter or exits if one of them is not valid.
+pub(crate) fn build_json_path_matcher(cli: &Cli) -> JsonPathMatcher {
+    let json_path = build_json_path(cli.search_expression.as_ref().unwrap());
+    JsonPathMatcher {
+        finder: RefCell::new(JsonPathFinder::new(Box::new(Value::Null), Box::new(json_path))),
+        value_filter: build_value_filter(cli),
+    }
+}
+
+fn build_json_path(search_expression: &str) -> JsonPathInst {
+    match JsonPathInst::from_str(search_expression) {
+        Ok(json_path) => json_path,
+        Err(e) => {
+            eprintln!("Invalid json path expression '{}': {}", search_expression, e);
+            process::exit(0x0001);
+        }
+    }
+}
+
+/// Additional condition on the values selected by the json path.
+enum ValueFilter {
+    Text(Regex),
+    Compare(String, f64),
+}
+
+impl ValueFilter {
+    /// A leaf matches the text filter on its textual form and the comparison if it is a number or a numeric string.
+    /// Objects and arrays match if any of their leaves match.
+    fn matches(&self, value: &Value) -> bool {
+        match value {
+            Value::Object(map) => map.values().any(|v| self.matches(v)),
+            Value::Array(items) => items.iter().any(|v| self.matches(v)),
+            _ => {
+                let text = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string()
+                };
+                match self {
+                    ValueFilter::Text(re) => matches!(re.is_match(&text), Ok(true)),
+                    ValueFilter::Compare(op, number) => match text.trim().parse::<f64>() {
+                        Ok(n) => match op.as_str() {
+                            ">" => n > *number,
+                            ">=" => n >= *number,
+                            "<" => n < *number,
+                            "<=" => n <= *number,
+                            "==" => n == *number,
+                            "!=" => n != *number,
+                            _ => false
+                        },
+                        Err(_) => false
+                    }
+                }
             }
         }
+    }
+}
+
+/// Parses the value filter of the command line or exits if it is not valid.
+/// Filters starting with a comparison operator, e.g. '>= 30', are numeric, filters starting with 're:' are regular
+/// expressions and all other filters are literal text. The text filters honour --ignore-case and --word.
+fn build_value_filter(cli: &Cli) -> Option<ValueFilter> {
+    let filter = cli.value_filter.as_ref()?;
+    if let Some(regex) = filter.strip_prefix("re:") {
+        if let Err(e) = Regex::new(regex) {
+            eprintln!("Invalid value filter regex '{}': {}", regex, e);
+            process::exit(0x0001);
+        }
+        return Some(ValueFilter::Text(build_regex(cli, regex, false)));
+    }
+    for op in [">=", "<=", "==", "!=", ">", "<"] {
+        if let Some(number) = filter.strip_prefix(op) {
+            return match number.trim().parse::<f64>() {
+                Ok(n) => Some(ValueFilter::Compare(op.to_string(), n)),
+                Err(_) => {
+                    eprintln!("Invalid number in value filter '{}'", filter);
+                    process::exit(0x0001);
+                }
+            };
+        }
+    }
+    Some(ValueFilter::Text(build_regex(cli, filter, true)))
+}
+
+pub(crate) fn process_file_with_json_path(path: PathBuf, cli: &Cli, matcher: &JsonPathMatcher,
+                                          output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().unwrap();
+    if let Some(kind) = archive_kind(&path) {
+        if let Err(e) = process_archive_with_json_path(&path, kind, cli, matcher, output) {
+            output.err_output(format!("{} :: {}", file_name, e).as_str());
+        }
+        return;
+    }
+    if cli.json_lines || is_json_lines(&path) {
+        match read_lines(&path) {
+            Ok(lines) => process_json_lines(file_name, None, lines, cli, matcher, output),
+            Err(e) => output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str())
+        }
+        return;
+    }
+    match fs::read_to_string(&path) {
+        Ok(content) => print_documents(file_name, None, &content, &extension_of(&path), cli, matcher, output),
         Err(e) => {
             output.err_output(format!("Error occurred: {:?}", e).as_str());
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+pub(crate) fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else {
+        None
+    }
+}
+
+/// Applies the json path to the json, yaml, toml and json lines entries of a zip or (gzipped) tar archive.
+/// Hits are reported as "archive :: entry :: concrete path :: value".
+fn process_archive_with_json_path(path: &Path, kind: ArchiveKind, cli: &Cli, matcher: &JsonPathMatcher,
+                                  output: &mut dyn OutputPrinter) -> Result<(), String> {
+    let file_name = path.to_str().unwrap();
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let process_entry = |entry_name: &str, reader: &mut dyn Read, output: &mut dyn OutputPrinter| {
+        let entry_path = Path::new(entry_name);
+        if !(cli.json_lines || is_json_lines(entry_path) || JSON_EXTENSIONS.contains(&extension_of(entry_path).as_str())) {
+            return;
+        }
+        let location = format!("{} :: {}", file_name, entry_name);
+        let mut content = String::new();
+        if let Err(e) = reader.read_to_string(&mut content) {
+            output.err_output(format!("Could not read {}: {}", location, e).as_str());
+        } else if cli.json_lines || is_json_lines(entry_path) {
+            process_json_lines(&location, Some(entry_name), content.lines().map(|l| Ok(l.to_string())), cli, matcher,
+                               output);
+        } else {
+            print_documents(&location, Some(entry_name), &content, &extension_of(entry_path), cli, matcher, output);
+        }
+    };
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+            for i in 0..archive.len() {
+                let mut entry = match archive.by_index(i) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        output.err_output(format!("{} :: {}", file_name, e).as_str());
+                        continue;
+                    }
+                };
+                if entry.is_file() {
+                    let entry_name = entry.name().to_string();
+                    process_entry(&entry_name, &mut entry, output);
+                }
+            }
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            let reader: Box<dyn Read> = match kind {
+                ArchiveKind::TarGz => Box::new(GzDecoder::new(file)),
+                _ => Box::new(file)
+            };
+            let mut archive = tar::Archive::new(reader);
+            for entry in archive.entries().map_err(|e| e.to_string())? {
+                let mut entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        output.err_output(format!("{} :: {}", file_name, e).as_str());
+                        continue;
+                    }
+                };
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                match entry.path() {
+                    Ok(entry_path) => {
+                        let entry_name = entry_path.to_string_lossy().to_string();
+                        process_entry(&entry_name, &mut entry, output);
+                    }
+                    Err(e) => output.err_output(format!("{} :: {}", file_name, e).as_str())
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses the content according to the extension and prints the matches of every document.
+/// Documents of multi-document yaml streams are reported with their position in the stream, empty documents included.
+fn print_documents(location: &str, entry: Option<&str>, content: &str, extension: &str, cli: &Cli,
+                   matcher: &JsonPathMatcher, output: &mut dyn OutputPrinter) {
+    let hit = HitFields { entry: entry.map(|e| e.to_string()), ..HitFields::default() };
+    match parse_documents(content, extension) {
+        Ok(documents) => {
+            let multiple = documents.iter().filter(|d| !d.is_null()).count() > 1;
+            for (index, document) in documents.into_iter().enumerate() {
+                // An empty stream or a trailing document separator yields a null document
+                if document.is_null() {
+                    continue;
+                }
+                let document_location = if multiple {
+                    format!("{} :: document {}", location, index + 1)
+                } else {
+                    location.to_string()
+                };
+                print_matches(&document_location, &hit, document, cli, matcher, output);
+            }
+        }
+        Err(e) => {
+            output.err_output(format!("Error occurred: Could not parse {}: {}", location, e).as_str());
+        }
+    }
+}
+
+fn extension_of(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+pub(crate) fn is_json_lines(path: &Path) -> bool {
+    JSON_LINES_EXTENSIONS.contains(&extension_of(path).as_str())
+}
+
+/// Applies the json path to every line, printing the matches of every record with its line number counted from 1.
+/// Malformed lines are counted as errors and the remaining lines are still processed.
+fn process_json_lines<L>(name: &str, entry: Option<&str>, lines: L, cli: &Cli, matcher: &JsonPathMatcher,
+                         output: &mut dyn OutputPrinter)
+    where L: Iterator<Item=std::io::Result<String>> {
+    for (index, line) in lines.enumerate() {
+        let linenumber = index + 1;
+        match line {
+            Ok(s) => {
+                if s.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(&s) {
+                    Ok(record) => {
+                        let hit = HitFields {
+                            entry: entry.map(|e| e.to_string()),
+                            line: Some(linenumber as u64),
+                            ..HitFields::default()
+                        };
+                        print_matches(&format!("{} :: {}", name, linenumber), &hit, record, cli, matcher, output);
+                    }
+                    Err(e) => {
+                        output.err_output(format!("{} :: {} :: Malformed json: {}", name, linenumber, e).as_str());
+                    }
+                }
+            }
+            Err(e) => {
+                output.err_output(format!("{} :: {} :: {}", name, linenumber, e).as_str());
+                // The reader cannot recover from an I/O error
+                if e.kind() != std::io::ErrorKind::InvalidData {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Prints every match of the json path in the document as "location :: concrete path :: value".
+/// Matches are skipped if there is a value filter which they do not pass.
+fn print_matches(location: &str, hit: &HitFields, document: Value, cli: &Cli, matcher: &JsonPathMatcher,
+                 output: &mut dyn OutputPrinter) {
+    let document = Box::new(document);
+    // The values stay at the same address when the box is moved into the finder
+    let mut paths = HashMap::new();
+    collect_paths(&document, "$".to_string(), &mut paths);
+    let mut finder = matcher.finder.borrow_mut();
+    finder.set_json(document);
+    for value in finder.find_slice() {
+        if matcher.value_filter.as_ref().map(|f| !f.matches(value)).unwrap_or(false) {
+            continue;
+        }
+        // Values computed by the json path, like the result of length(), are not part of the document
+        let concrete_path = paths.get(&(value as *const Value)).map(|p| p.as_str()).unwrap_or("?");
+        let formatted = if cli.pretty {
+            serde_json::to_string_pretty(value)
+        } else {
+            serde_json::to_string(value)
+        }.unwrap_or_else(|_| format!("{:?}", value));
+        let fields = HitFields { matched: Some(formatted.clone()), ..hit.clone() };
+        output.output_hit(format!("{} :: {} :: {}", location, concrete_path, formatted).as_str(), fields);
+    }
+}
+
+/// Maps the address of every value in the document to its normalized path, e.g. $.customers[3].name.
+fn collect_paths(value: &Value, path: String, paths: &mut HashMap<*const Value, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                collect_paths(child, format!("{}{}", path, key_segment(key)), paths);
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                collect_paths(child, format!("{}[{}]", path, index), paths);
+            }
+        }
+        _ => {}
+    }
+    paths.insert(value as *const Value, path);
+}
+
+fn key_segment(key: &str) -> String {
+    let is_identifier = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier {
+        format!(".{}", key)
+    } else {
+        format!("['{}']", key.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+}
+
+/// Parses json, yaml or toml depending on the extension.
+/// Yaml streams can contain multiple documents, all other formats produce a single document.
+/// Empty yaml documents are kept as null so that the documents keep their position in the stream.
+pub(crate) fn parse_documents(content: &str, extension: &str) -> Result<Vec<Value>, String> {
+    match extension {
+        "yaml" | "yml" => {
+            serde_yaml::Deserializer::from_str(content)
+                .map(|document| Value::deserialize(document).map_err(|e| e.to_string()))
+                .collect()
+        }
+        "toml" => {
+            let value: toml::Value = toml::from_str(content).map_err(|e| e.to_string())?;
+            Ok(vec![toml_to_json(value)])
+        }
+        _ => {
+            let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+            Ok(vec![value])
+        }
+    }
+}
+
+/// Converts a toml value to json. Dates and times become strings.
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(a) => Value::Array(a.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(t) => Value::Object(t.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect()),
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+
+use crate::{OutputPrinter, Statistics};
+use crate::result_printer::{print_msg, HitFields};
+
+/// Collects the parameters, hits, messages and errors and writes them as one json document with the statistics.
+pub(crate) struct JsonPrinter<'a> {
+    pub(crate) statistics: Statistics,
+    pub(crate) path: &'a Path,
+    pub(crate) file: &'a File,
+    pub(crate) parameters: Map<String, Value>,
+    pub(crate) hits: Vec<Value>,
+    pub(crate) messages: Vec<Value>,
+    pub(crate) errors: Vec<Value>,
+}
+
+impl JsonPrinter<'_> {
+    fn print_to_file(&mut self, msg: &str, what: &str) {
+        print_msg(self.file, self.path, msg, what);
+    }
+}
+
+/// Splits the message into its fields. A field starting a json object or array, like the records of query mode,
+/// is kept as json together with the rest of the message, which may contain the field separator.
+fn fields_of(msg: &str) -> Value {
+    let parts = msg.split(" :: ").collect::<Vec<&str>>();
+    let mut fields = vec![];
+    for (index, part) in parts.iter().enumerate() {
+        if part.starts_with('{') || part.starts_with('[') {
+            if let Ok(value) = serde_json::from_str::<Value>(&parts[index..].join(" :: ")) {
+                fields.push(value);
+                break;
+            }
+        }
+        fields.push(Value::String(part.to_string()));
+    }
+    Value::Array(fields)
+}
+
+impl OutputPrinter for JsonPrinter<'_> {
+    fn print_param_map(&mut self, map: HashMap<String, String>) {
+        for (key, value) in map {
+            self.parameters.insert(key, Value::String(value));
+        }
+    }
+
+    fn output_with_stats(&mut self, msg: &str) {
+        self.output_hit(msg, HitFields::default());
+    }
+
+    /// Writes the hit as an object with the message and the fields which are known. A matched json value, like the
+    /// records of query mode, is kept as json.
+    fn output_hit(&mut self, msg: &str, fields: HitFields) {
+        let mut hit = Map::new();
+        hit.insert("message".to_string(), Value::String(msg.to_string()));
+        if let Some(entry) = fields.entry {
+            hit.insert("entry".to_string(), Value::String(entry));
+        }
+        if let Some(line) = fields.line {
+            hit.insert("line".to_string(), Value::from(line));
+        }
+        if let Some(offset) = fields.offset {
+            hit.insert("offset".to_string(), Value::from(offset));
+        }
+        if let Some(matched) = fields.matched {
+            let value = if matched.starts_with('{') || matched.starts_with('[') {
+                serde_json::from_str::<Value>(&matched).unwrap_or(Value::String(matched))
+            } else {
+                Value::String(matched)
+            };
+            hit.insert("matched".to_string(), value);
+        }
+        self.hits.push(Value::Object(hit));
+        self.statistics.increase_hits();
+    }
+
+    fn output(&mut self, msg: &str) {
+        self.messages.push(fields_of(msg));
+    }
+
+    fn err_output(&mut self, msg: &str) {
+        self.errors.push(Value::String(msg.to_string()));
+        self.statistics.increase_errors();
+    }
+
+    fn get_name(&self) -> &str {
+        "JsonPrinter"
+    }
+
+    fn print_stats(&mut self) {
+        let document = json!({
+            "parameters": self.parameters,
+            "hits": self.hits,
+            "messages": self.messages,
+            "errors": self.errors,
+            "stats": {
+                "hits": self.statistics.hits,
+                "errors": self.statistics.errors,
+            },
+        });
+        let content = serde_json::to_string_pretty(&document).unwrap_or_default();
+        self.print_to_file(&content, "message");
+    }
+}
@@ -13,12 +13,32 @@ use colored::Colorize;
 use fancy_regex::Regex;
 
 use crate::cli::{Cli, Mode, Output};
-use crate::finders::find_simple;
+use crate::bytes_search::{build_byte_pattern, process_bytes_search};
+use crate::class_search::process_class_search;
+use crate::csv_search::process_csv_search;
+use crate::dedupe::process_dedupe;
+use crate::duplicates::process_duplicates;
+use crate::email_search::process_email_search;
+use crate::exec::{Executor, HitCollector};
+use crate::file_actions::FileActions;
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
 use crate::html_printer::HtmlPrinter;
 use crate::io_ops::{LINE_ENDING, read_lines};
-use crate::json_path_search::process_file_with_json_path;
-use crate::pdf_search::process_pdf_simple_search;
-use crate::result_printer::{FilePrinter, OutputPrinter, Statistics, StdPrinter};
+use crate::json_printer::JsonPrinter;
+use crate::json_path_search::{build_json_path_matcher, process_file_with_json_path};
+use crate::maven_search::process_maven_search;
+use crate::office_search::process_office_search;
+use crate::pdf_metadata::process_pdf_metadata_search;
+use crate::pdf_search::{process_pdf_regex_search, process_pdf_simple_search};
+use crate::pii_search::{build_detectors, process_pii_search};
+use crate::query_search::{build_query, process_query_search};
+use crate::redact::{resolve_redacted_dir, Redaction};
+use crate::result_printer::{FilePrinter, HitFields, OutputPrinter, Statistics, StdPrinter};
+use crate::secrets_search::{process_secrets_search, select_secret_rules};
+use crate::sqlite_search::process_sqlite_search;
+use crate::text_cache::TextCache;
+use crate::text_search::process_text_search;
+use crate::xpath_search::{build_xpath, build_xpath_context, process_file_with_xpath};
 
 use self::glob::glob;
 
@@ -27,27 +47,76 @@ mod io_ops;
 mod result_printer;
 mod json_path_search;
 mod pdf_search;
+mod pdf_metadata;
+mod office_search;
 mod finders;
 mod html_printer;
+mod text_cache;
+mod xpath_search;
+mod csv_search;
+mod query_search;
+mod sqlite_search;
+mod email_search;
+mod text_search;
+mod class_search;
+mod maven_search;
+mod duplicates;
+mod dedupe;
+mod bytes_search;
+mod secrets_search;
+mod pii_search;
+mod redact;
+mod exec;
+mod file_actions;
+mod json_printer;
 
-fn read_files(cli: &Cli, process_fn: fn(PathBuf, &Option<String>, output: &mut dyn OutputPrinter) -> (), output: &mut dyn OutputPrinter) {
+/// Processes every file of the glob pattern. The processing function may capture state which is prepared once for all
+/// files, like a parsed search expression.
+fn read_files<F>(cli: &Cli, process_fn: F, output: &mut dyn OutputPrinter)
+    where F: Fn(PathBuf, &Cli, &mut dyn OutputPrinter) {
     let glob_pattern = &cli.glob_pattern;
-    let search_expression = &cli.search_expression;
     let expected = format!("Failed to read glob pattern {}", glob_pattern);
-    for entry in glob(glob_pattern).expect(&expected) {
-        match entry {
-            Ok(path) => {
-                process_fn(path, search_expression, output);
+    let mut redaction = Redaction::from_cli(cli);
+    let mut executor = Executor::from_cli(cli);
+    let mut file_actions = FileActions::from_cli(cli);
+    for path in glob(glob_pattern).expect(&expected).flatten() {
+        if redaction.is_none() && executor.is_none() && file_actions.is_none() {
+            process_fn(path, cli, output);
+            continue;
+        }
+        let mut collector = HitCollector { inner: output, hits: vec![] };
+        process_fn(path.clone(), cli, &mut collector);
+        let hits = collector.hits;
+        // Redacted before the command or the file actions may change the file
+        if let Some(redaction) = &mut redaction {
+            if !hits.is_empty() {
+                redaction.file_processed(&path, output);
+            }
+        }
+        if let Some(executor) = &mut executor {
+            executor.file_processed(path.to_str().unwrap(), &hits, output);
+        }
+        if let Some(file_actions) = &mut file_actions {
+            if !hits.is_empty() {
+                file_actions.add(path);
             }
-            Err(_) => {}
         }
     }
+    if let Some(redaction) = &redaction {
+        redaction.finish(cli, output);
+    }
+    if let Some(executor) = &mut executor {
+        executor.finish(output);
+    }
+    if let Some(file_actions) = &file_actions {
+        file_actions.apply(cli, output);
+    }
 }
 
-fn process_path_simple(path: PathBuf, _: &Option<String>, output: &mut dyn OutputPrinter) {
+fn process_path_simple(path: PathBuf, _: &Cli, output: &mut dyn OutputPrinter) {
     match path.to_str() {
         Some(s) => {
-            output.output_with_stats(s);
+            output.output_hit(s, HitFields { matched: Some(s.to_string()), ..HitFields::default() });
         }
         None => {
             output.err_output("Nothing to print")
@@ -55,32 +124,13 @@ fn process_path_simple(path: PathBuf, _: &Option<String>, output: &mut dyn Outpu
     }
 }
 
-fn find_regex(content: &str, search_filter: &Regex) -> bool {
-    let result = search_filter.find(content);
-    if result.is_ok() {
-        let match_option = result.unwrap();
-        match match_option {
-            Some(_) => {
-                // println!("Start position: {}", m.start());
-                return true;
-            }
-            None => {}
-        }
-    }
-    return false;
-}
-
-fn process_path_with_expression(path: PathBuf, search_expression_option: &Option<String>,
-                                output: &mut dyn OutputPrinter) {
+fn process_path_with_expression(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
     match path.to_str() {
         Some(s) => {
-            match search_expression_option {
-                Some(search_filter) => {
-                    if find_simple(s, search_filter) {
-                        output.output_with_stats(s);
-                    }
+            if let Some(search_filter) = &cli.search_expression {
+                if find_simple(s, search_filter) {
+                    output.output_hit(s, HitFields { matched: Some(s.to_string()), ..HitFields::default() });
                 }
-                _ => ()
             }
         }
         None => {
@@ -89,18 +139,15 @@ fn process_path_with_expression(path: PathBuf, search_expression_option: &Option
     }
 }
 
-fn process_zip_with_expression(path: PathBuf, search_expression_option: &Option<String>, output: &mut dyn OutputPrinter) {
-    process_zip_with_expression_generic(path, search_expression_option, find_simple, output);
+fn process_zip_with_expression(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    process_zip_with_expression_generic(path, &cli.search_expression, find_simple, output);
 }
 
-fn process_zip_with_regex(path: PathBuf, search_expression_option: &Option<String>, output: &mut dyn OutputPrinter) {
-    match search_expression_option {
-        Some(search_expression) => {
-            let re = Regex::new(search_expression).expect("Invalid regex");
-            process_zip_with_expression_generic(path, &Some(re), find_regex,
-                                                output);
-        }
-        None => {}
+fn process_zip_with_regex(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    if let Some(search_expression) = &cli.search_expression {
+        let re = Regex::new(search_expression).expect("Invalid regex");
+        process_zip_with_expression_generic(path, &Some(re), find_regex,
+                                            output);
     }
 }
 
@@ -118,7 +165,12 @@ fn process_zip_with_expression_generic<T>(path: PathBuf, search_expression_optio
                 let file = archive.by_index(i).unwrap();
                 let file_name = file.name();
                 if find_func(file_name, search_filter) {
-                    output.output_with_stats(format!("{} :: {}", main_file_path, file_name).as_str());
+                    let fields = HitFields {
+                        entry: Some(file_name.to_string()),
+                        matched: Some(file_name.to_string()),
+                        ..HitFields::default()
+                    };
+                    output.output_hit(format!("{} :: {}", main_file_path, file_name).as_str(), fields);
                 }
             }
         }
@@ -128,17 +180,19 @@ fn process_zip_with_expression_generic<T>(path: PathBuf, search_expression_optio
     }
 }
 
-fn process_line_search(path: PathBuf, search_expression_option: &Option<String>, output: &mut dyn OutputPrinter) {
-    process_line_search_generic(path, search_expression_option, find_simple, output);
+fn process_line_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    if needs_regex(cli) {
+        let re = build_regex(cli, cli.search_expression.as_ref().unwrap(), true);
+        process_line_search_generic(path, &Some(re), find_regex, output);
+    } else {
+        process_line_search_generic(path, &cli.search_expression, find_simple, output);
+    }
 }
 
-fn process_regex_search(path: PathBuf, search_expression_option: &Option<String>, output: &mut dyn OutputPrinter) {
-    match search_expression_option {
-        Some(search_expression) => {
-            let re = Regex::new(search_expression).expect("Invalid regex");
-            process_line_search_generic(path, &Some(re), find_regex, output);
-        }
-        None => {}
+fn process_regex_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    if let Some(search_expression) = &cli.search_expression {
+        let re = build_regex(cli, search_expression, false);
+        process_line_search_generic(path, &Some(re), find_regex, output);
     }
 }
 
@@ -152,11 +206,16 @@ fn process_line_search_generic<T>(path: PathBuf, search_expression_option: &Opti
             for (linenumber, line) in lines.enumerate() {
                 if let Ok(s) = line {
                     if search_fn(&s, search_filter) {
-                        let mut content = format!("{}", main_file_path);
+                        let mut content = main_file_path.to_string();
                         if output.get_name().eq("StdPrinter") {
                             content = content.bold().parse().unwrap();
                         }
-                        output.output_with_stats(format!("{} :: {} :: {}", content, linenumber, s.trim()).as_str());
+                        let fields = HitFields {
+                            line: Some(linenumber as u64 + 1),
+                            matched: Some(s.trim().to_string()),
+                            ..HitFields::default()
+                        };
+                        output.output_hit(format!("{} :: {} :: {}", content, linenumber, s.trim()).as_str(), fields);
                     }
                 }
             }
@@ -172,16 +231,16 @@ fn handle_missing_search_expression() {
     process::exit(0x0001);
 }
 
-fn execute_on_expression(
-    args: &Cli, missing_func: fn() -> (),
-    process_fn: fn(PathBuf, &Option<String>, output: &mut dyn OutputPrinter),
+fn execute_on_expression<F>(
+    args: &Cli, missing_func: fn(),
+    process_fn: F,
     output: &mut dyn OutputPrinter,
-) {
+) where F: Fn(PathBuf, &Cli, &mut dyn OutputPrinter) {
     let search_expression = &args.search_expression;
     if search_expression.is_none() {
         missing_func()
     } else {
-        read_files(&args, process_fn, output);
+        read_files(args, process_fn, output);
     }
 }
 
@@ -197,76 +256,116 @@ fn main() {
     let mut printer: &mut dyn OutputPrinter = &mut StdPrinter { statistics };
     let mut file_printer_obj;
     let mut html_printer_obj;
+    let mut json_printer_obj;
     let file;
     let mut std_printer = StdPrinter { statistics };
 
-    match output_option {
-        Some(output) => {
-            match output {
-                Output::Console => {
-                    printer = &mut std_printer
+    if let Some(output) = output_option {
+        match output {
+            Output::Console => {
+                printer = &mut std_printer
+            }
+            Output::File => {
+                match file_option {
+                    Some(f) => {
+                        let file_path = create_file(f);
+                        let written_file_result = prepare_file(&file_path);
+                        file = written_file_result.unwrap();
+                        file_printer_obj = FilePrinter {
+                            statistics,
+                            path: file_path,
+                            file: &file,
+                        };
+                        printer = &mut file_printer_obj;
+                    }
+                    None => {
+                        // The user probably forgot about the file
+                        printer = &mut std_printer
+                    }
                 }
-                Output::File => {
-                    match file_option {
-                        Some(f) => {
-                            let file_path = create_file(f);
-                            let written_file_result = prepare_file(&file_path);
-                            file = written_file_result.unwrap();
-                            file_printer_obj = FilePrinter {
-                                statistics,
-                                path: &file_path,
-                                file: &file,
-                            };
-                            printer = &mut file_printer_obj;
-                        }
-                        None => {
-                            // The user probably forgot about the file
-                            printer = &mut std_printer
-                        }
+            }
+            Output::Html => {
+                match file_option {
+                    Some(f) => {
+                        let file_path = create_file(f);
+                        let written_file_result = prepare_file(&file_path);
+                        file = written_file_result.unwrap();
+                        html_printer_obj = HtmlPrinter {
+                            statistics,
+                            path: file_path,
+                            file: &file,
+                        };
+                        printer = &mut html_printer_obj;
+                    }
+                    None => {
+                        // The user probably forgot about the file
+                        printer = &mut std_printer
                     }
                 }
-                Output::HTML => {
-                    match file_option {
-                        Some(f) => {
-                            let file_path = create_file(f);
-                            let written_file_result = prepare_file(&file_path);
-                            file = written_file_result.unwrap();
-                            html_printer_obj = HtmlPrinter {
-                                statistics,
-                                path: &file_path,
-                                file: &file,
-                            };
-                            printer = &mut html_printer_obj;
-                        }
-                        None => {
-                            // The user probably forgot about the file
-                            printer = &mut std_printer
-                        }
+            }
+            Output::Json => {
+                match file_option {
+                    Some(f) => {
+                        let file_path = create_file(f);
+                        let written_file_result = prepare_file(&file_path);
+                        file = written_file_result.unwrap();
+                        json_printer_obj = JsonPrinter {
+                            statistics,
+                            path: file_path,
+                            file: &file,
+                            parameters: serde_json::Map::new(),
+                            hits: vec![],
+                            messages: vec![],
+                            errors: vec![],
+                        };
+                        printer = &mut json_printer_obj;
+                    }
+                    None => {
+                        // The user probably forgot about the file
+                        printer = &mut std_printer
                     }
                 }
             }
         }
-        None => {}
     }
 
     print_cmd_options(&args, printer);
+    let cache = TextCache::from_cli(&args);
+    if cache.is_none() && (args.clear_cache || args.cache_max_size.is_some()) {
+        eprintln!("Please enter the cache directory with e.g: '--cache-dir .file_search_cache'");
+        process::exit(0x0001);
+    }
+    if let Some(cache) = &cache {
+        if args.clear_cache {
+            if let Err(e) = cache.clear() {
+                printer.err_output(format!("Could not clear cache: {}", e).as_str());
+            }
+        }
+    }
+    // Fail on invalid commands, file actions and redaction options before searching
+    Executor::from_cli(&args);
+    FileActions::from_cli(&args);
+    Redaction::from_cli(&args);
     process_all_modes(&args, search_expression, mode, printer);
+    if let (Some(cache), Some(max_size)) = (&cache, args.cache_max_size) {
+        if let Err(e) = cache.shrink_to(max_size * 1024 * 1024) {
+            printer.err_output(format!("Could not shrink cache: {}", e).as_str());
+        }
+    }
     printer.print_stats();
 }
 
 fn prepare_file(file_path: &&Path) -> std::io::Result<File> {
     OpenOptions::new()
-        .write(true)
         .create(true)
         .append(true)
-        .open(&file_path)
+        .open(file_path)
 }
 
 fn create_file(f: &String) -> &Path {
     let file_path = Path::new(f);
     if file_path.exists() {
-        fs::remove_file(file_path).expect(
-            format!("Could not remove {}.", file_path.display().to_string()).as_str());
+        fs::remove_file(file_path).unwrap_or_else(|_| panic!("Could not remove {}.", file_path.display()));
     }
     file_path
 }
@@ -281,6 +380,60 @@ fn print_cmd_options(args: &Cli, printer: &mut dyn OutputPrinter) {
     if args.file.is_some() {
         print_map.insert("File".to_string(), format!("{:?}", args.file.clone().unwrap()));
     }
+    if args.ignore_case {
+        print_map.insert("Ignore case".to_string(), format!("{:?}", args.ignore_case));
+    }
+    if args.word {
+        print_map.insert("Word".to_string(), format!("{:?}", args.word));
+    }
+    if !args.namespace.is_empty() {
+        print_map.insert("Namespace".to_string(), format!("{:?}", args.namespace));
+    }
+    if args.value_filter.is_some() {
+        print_map.insert("Value".to_string(), format!("{:?}", args.value_filter.clone().unwrap()));
+    }
+    if !args.column.is_empty() {
+        print_map.insert("Column".to_string(), format!("{:?}", args.column));
+    }
+    if !args.table.is_empty() {
+        print_map.insert("Table".to_string(), format!("{:?}", args.table));
+    }
+    if let Some(email_part) = &args.email_part {
+        print_map.insert("Part".to_string(), format!("{:?}", email_part));
+    }
+    if !args.text_element.is_empty() {
+        print_map.insert("Element".to_string(), format!("{:?}", args.text_element));
+    }
+    if !args.class_element.is_empty() {
+        print_map.insert("Class".to_string(), format!("{:?}", args.class_element));
+    }
+    if !args.pii_type.is_empty() {
+        print_map.insert("PII".to_string(), format!("{:?}", args.pii_type));
+    }
+    if let Some(exec) = &args.exec {
+        print_map.insert("Exec".to_string(), format!("{:?}", exec));
+    }
+    if let Some(exec_batch) = &args.exec_batch {
+        print_map.insert("Batch".to_string(), format!("{:?}", exec_batch));
+    }
+    if let Some(copy_to) = &args.copy_to {
+        print_map.insert("Copy to".to_string(), format!("{:?}", copy_to));
+    }
+    if let Some(move_to) = &args.move_to {
+        print_map.insert("Move to".to_string(), format!("{:?}", move_to));
+    }
+    if args.delete {
+        print_map.insert("Delete".to_string(), format!("{:?}", args.delete));
+    }
+    if let Some(redact) = &args.redact {
+        print_map.insert("Redact".to_string(), format!("{:?}", redact));
+    }
+    if let Some(rules) = &args.rules {
+        print_map.insert("Rules".to_string(), format!("{:?}", rules));
+    }
+    if args.cache_dir.is_some() {
+        print_map.insert("Cache".to_string(), format!("{:?}", args.cache_dir.clone().unwrap()));
+    }
     printer.print_param_map(print_map);
 }
 
@@ -289,38 +442,130 @@ fn process_all_modes(args: &Cli,
                      mode: &Mode, printer: &mut dyn OutputPrinter) {
     match mode {
         Mode::FileName => {
-            read_files(&args,
+            read_files(args,
                        if search_expression.is_none() { process_path_simple } else { process_path_with_expression }, printer);
         }
         Mode::Zip => {
-            execute_on_expression(&args,
+            execute_on_expression(args,
                                   handle_missing_search_expression,
                                   process_zip_with_expression, printer);
         }
         Mode::LineSearch => {
-            execute_on_expression(&args,
+            execute_on_expression(args,
                                   handle_missing_search_expression,
                                   process_line_search, printer);
         }
         Mode::LineRegexSearch => {
-            execute_on_expression(&args,
+            execute_on_expression(args,
                                   handle_missing_search_expression,
                                   process_regex_search, printer);
         }
         Mode::ZipRegex => {
-            execute_on_expression(&args,
+            execute_on_expression(args,
                                   handle_missing_search_expression,
                                   process_zip_with_regex, printer);
         }
         Mode::JsonPath => {
-            execute_on_expression(&args,
-                                  handle_missing_search_expression,
-                                  process_file_with_json_path, printer);
+            match search_expression {
+                Some(_) => {
+                    let matcher = build_json_path_matcher(args);
+                    read_files(args, |path, cli, output| process_file_with_json_path(path, cli, &matcher, output),
+                               printer);
+                }
+                None => handle_missing_search_expression()
+            }
         }
         Mode::PdfSearch => {
-            execute_on_expression(&args,
+            execute_on_expression(args,
                                   handle_missing_search_expression,
                                   process_pdf_simple_search, printer);
         }
+        Mode::PdfRegex => {
+            execute_on_expression(args,
+                                  handle_missing_search_expression,
+                                  process_pdf_regex_search, printer);
+        }
+        Mode::PdfMetadata => {
+            read_files(args, process_pdf_metadata_search, printer);
+        }
+        Mode::OfficeSearch => {
+            execute_on_expression(args,
+                                  handle_missing_search_expression,
+                                  process_office_search, printer);
+        }
+        Mode::Xpath => {
+            match search_expression {
+                Some(search_expression) => {
+                    let xpath = build_xpath(search_expression);
+                    let context = build_xpath_context(args);
+                    read_files(args, |path, _, output| process_file_with_xpath(path, &xpath, &context, output),
+                               printer);
+                }
+                None => handle_missing_search_expression()
+            }
+        }
+        Mode::Csv => {
+            execute_on_expression(args,
+                                  handle_missing_search_expression,
+                                  process_csv_search, printer);
+        }
+        Mode::Query => {
+            match search_expression {
+                Some(_) => {
+                    let query = build_query(args);
+                    read_files(args, |path, cli, output| process_query_search(path, cli, &query, output), printer);
+                }
+                None => handle_missing_search_expression()
+            }
+        }
+        Mode::Sqlite => {
+            execute_on_expression(args,
+                                  handle_missing_search_expression,
+                                  process_sqlite_search, printer);
+        }
+        Mode::Email => {
+            execute_on_expression(args,
+                                  handle_missing_search_expression,
+                                  process_email_search, printer);
+        }
+        Mode::Text => {
+            execute_on_expression(args,
+                                  handle_missing_search_expression,
+                                  process_text_search, printer);
+        }
+        Mode::ClassSearch => {
+            execute_on_expression(args,
+                                  handle_missing_search_expression,
+                                  process_class_search, printer);
+        }
+        Mode::Maven => {
+            read_files(args, process_maven_search, printer);
+        }
+        Mode::Duplicates => {
+            process_duplicates(args, printer);
+        }
+        Mode::Dedupe => {
+            process_dedupe(args, printer);
+        }
+        Mode::Bytes => {
+            match search_expression {
+                Some(search_expression) => {
+                    let pattern = build_byte_pattern(search_expression);
+                    read_files(args, |path, _, output| process_bytes_search(path, &pattern, output), printer);
+                }
+                None => handle_missing_search_expression()
+            }
+        }
+        Mode::Secrets => {
+            let rules = select_secret_rules(args);
+            read_files(args, |path, _, output| process_secrets_search(path, &rules, output), printer);
+        }
+        Mode::Pii => {
+            if args.redacted_dir.is_some() && args.redact.is_none() {
+                resolve_redacted_dir(args);
+            }
+            let detectors = build_detectors(&args.pii_type);
+            read_files(args, |path, cli, output| process_pii_search(path, cli, &detectors, output), printer);
+        }
     }
 }
@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+use fancy_regex::Regex;
+use zip::ZipArchive;
+
+use crate::cli::Cli;
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+const MANIFEST: &str = "META-INF/MANIFEST.MF";
+
+/// Attributes of the main section of the manifest which are reported.
+const MANIFEST_KEYS: [&str; 6] = ["Implementation-Title", "Implementation-Version", "Implementation-Vendor",
+    "Automatic-Module-Name", "Bundle-SymbolicName", "Bundle-Version"];
+
+const COORDINATE_KEYS: [&str; 3] = ["groupId", "artifactId", "version"];
+
+pub(crate) fn process_maven_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    match &cli.search_expression {
+        Some(search_expression) => {
+            // Class names are shown with dots, so names in the internal form with slashes are converted
+            let class_expression = search_expression.replace('/', ".");
+            if needs_regex(cli) {
+                let re = build_regex(cli, search_expression, true);
+                let class_re = build_regex(cli, &class_expression, true);
+                process_maven_search_generic(path, Some((&re, &class_re)), find_regex, output);
+            } else {
+                process_maven_search_generic(path, Some((search_expression, &class_expression)), find_simple, output);
+            }
+        }
+        None => {
+            process_maven_search_generic::<Regex>(path, None, find_regex, output);
+        }
+    }
+}
+
+/// Prints "jar :: entry :: field :: value" for the matching Maven coordinates and manifest attributes and
+/// "jar :: class entry :: coordinates" for the class entries whose class name matches the class filter.
+/// All fields and no classes are printed if there are no filters.
+fn process_maven_search_generic<T>(path: PathBuf, search_filters: Option<(&T, &T)>,
+                                   find_fn: fn(&str, &T) -> bool,
+                                   output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().unwrap();
+    let archive = File::open(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|f| ZipArchive::new(f).map_err(|e| e.to_string()));
+    let mut archive = match archive {
+        Ok(archive) => archive,
+        Err(e) => {
+            output.err_output(format!("{} :: {}", file_name, e).as_str());
+            return;
+        }
+    };
+    let fields = extract_fields(file_name, &mut archive, output);
+    let matches = |value: &str| search_filters.map(|(filter, _)| find_fn(value, filter)).unwrap_or(true);
+    for (entry, key, value) in &fields {
+        if matches(value) {
+            let hit_fields = HitFields {
+                entry: Some(entry.to_string()),
+                matched: Some(value.to_string()),
+                ..HitFields::default()
+            };
+            output.output_hit(format!("{} :: {} :: {} :: {}", file_name, entry, key, value).as_str(), hit_fields);
+        }
+    }
+    if let Some((_, class_filter)) = search_filters {
+        let coordinates = coordinates(&fields);
+        for entry_name in archive.file_names().filter(|name| name.ends_with(".class")) {
+            let class_name = entry_name.trim_end_matches(".class").replace('/', ".");
+            if find_fn(&class_name, class_filter) {
+                let hit_fields = HitFields {
+                    entry: Some(entry_name.to_string()),
+                    matched: Some(class_name),
+                    ..HitFields::default()
+                };
+                output.output_hit(format!("{} :: {} :: {}", file_name, entry_name, coordinates).as_str(), hit_fields);
+            }
+        }
+    }
+}
+
+type Field = (String, String, String);
+
+/// Reads the manifest and the pom.properties of every artifact in the jar. The pom.xml is only read
+/// for artifacts without pom.properties. Entries which cannot be read or parsed are reported as errors and skipped.
+fn extract_fields(file_name: &str, archive: &mut ZipArchive<File>, output: &mut dyn OutputPrinter) -> Vec<Field> {
+    let mut fields = vec![];
+    if let Some(manifest) = read_entry(file_name, archive, MANIFEST, output) {
+        for (key, value) in parse_manifest(&manifest) {
+            if MANIFEST_KEYS.contains(&key.as_str()) {
+                fields.push((MANIFEST.to_string(), key, value));
+            }
+        }
+    }
+    let mut maven_entries = archive.file_names()
+        .filter(|name| name.starts_with("META-INF/maven/")
+            && (name.ends_with("/pom.properties") || name.ends_with("/pom.xml")))
+        .map(|name| name.to_string())
+        .collect::<Vec<String>>();
+    maven_entries.sort();
+    let with_properties = maven_entries.iter()
+        .filter_map(|name| name.strip_suffix("/pom.properties"))
+        .map(|dir| dir.to_string())
+        .collect::<HashSet<String>>();
+    for name in &maven_entries {
+        let content = match read_entry(file_name, archive, name, output) {
+            Some(content) => content,
+            None => continue
+        };
+        if name.ends_with("/pom.properties") {
+            for (key, value) in parse_properties(&content) {
+                if COORDINATE_KEYS.contains(&key.as_str()) {
+                    fields.push((name.clone(), key, value));
+                }
+            }
+        } else if !with_properties.contains(name.trim_end_matches("/pom.xml")) {
+            match parse_pom(&content) {
+                Ok(coordinates) => fields.extend(coordinates.into_iter().map(|(key, value)| (name.clone(), key, value))),
+                Err(e) => output.err_output(format!("{} :: {}: {}", file_name, name, e).as_str())
+            }
+        }
+    }
+    fields
+}
+
+fn read_entry(file_name: &str, archive: &mut ZipArchive<File>, name: &str,
+              output: &mut dyn OutputPrinter) -> Option<String> {
+    let mut entry = archive.by_name(name).ok()?;
+    let mut bytes = vec![];
+    if let Err(e) = entry.read_to_end(&mut bytes) {
+        output.err_output(format!("{} :: {}: {}", file_name, name, e).as_str());
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Attributes of the main section. Lines starting with a space continue the previous line.
+fn parse_manifest(content: &str) -> Vec<(String, String)> {
+    let mut attributes: Vec<(String, String)> = vec![];
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some(continuation) = line.strip_prefix(' ') {
+            if let Some((_, value)) = attributes.last_mut() {
+                value.push_str(continuation);
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            attributes.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    attributes
+}
+
+fn parse_properties(content: &str) -> Vec<(String, String)> {
+    content.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.starts_with('#') && !line.starts_with('!'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Coordinates of the project, falling back to the parent for the group and version.
+fn parse_pom(content: &str) -> Result<Vec<(String, String)>, String> {
+    let options = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+    let document = roxmltree::Document::parse_with_options(content, options).map_err(|e| e.to_string())?;
+    let project = document.root_element();
+    let child_text = |node: roxmltree::Node, name: &str| node.children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string());
+    let parent = project.children().find(|n| n.is_element() && n.tag_name().name() == "parent");
+    let coordinates = COORDINATE_KEYS.iter()
+        .filter_map(|key| {
+            let value = child_text(project, key).or_else(|| match *key {
+                "artifactId" => None,
+                _ => parent.and_then(|p| child_text(p, key))
+            })?;
+            Some((key.to_string(), value))
+        })
+        .collect();
+    Ok(coordinates)
+}
+
+/// The groupId:artifactId:version of the artifacts in the jar, used to tell which artifact a class belongs to.
+fn coordinates(fields: &[Field]) -> String {
+    let mut artifacts: Vec<String> = vec![];
+    let mut entries = fields.iter()
+        .filter(|(entry, _, _)| entry != MANIFEST)
+        .map(|(entry, _, _)| entry.as_str())
+        .collect::<Vec<&str>>();
+    entries.dedup();
+    for entry in entries {
+        let value = |key: &str| fields.iter()
+            .find(|(e, k, _)| e == entry && k == key)
+            .map(|(_, _, v)| v.as_str())
+            .unwrap_or("?");
+        artifacts.push(format!("{}:{}:{}", value("groupId"), value("artifactId"), value("version")));
+    }
+    if artifacts.is_empty() {
+        let version = fields.iter()
+            .find(|(_, key, _)| key == "Implementation-Version")
+            .map(|(_, _, v)| v.as_str())
+            .unwrap_or("?");
+        artifacts.push(format!("?:?:{}", version));
+    }
+    artifacts.join(", ")
+}
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use roxmltree::{Document, Node};
+use zip::ZipArchive;
+
+use crate::cli::Cli;
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::text_cache::extract_cached;
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+const WORD_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const DRAWING_NS: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+const SPREADSHEET_NS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const RELATIONSHIPS_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const PACKAGE_RELATIONSHIPS_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+const ODF_TEXT_NS: &str = "urn:oasis:names:tc:opendocument:xmlns:text:1.0";
+const ODF_TABLE_NS: &str = "urn:oasis:names:tc:opendocument:xmlns:table:1.0";
+
+type OfficeArchive = ZipArchive<File>;
+
+/// A piece of text of an office document together with its location, e.g. "paragraph 3", "Sheet1!B2" or "slide 4".
+pub(crate) type LocatedText = (String, String);
+
+pub(crate) fn process_office_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    let search_expression = cli.search_expression.as_ref().unwrap();
+    if needs_regex(cli) {
+        let re = build_regex(cli, search_expression, true);
+        process_office_search_generic(path, cli, &re, find_regex, output);
+    } else {
+        process_office_search_generic(path, cli, search_expression, find_simple, output);
+    }
+}
+
+fn process_office_search_generic<T>(path: PathBuf, cli: &Cli, search_filter: &T,
+                                    find_fn: fn(&str, &T) -> bool,
+                                    output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().expect("Could not extract file name from path");
+    match extract_cached(cli, &path, extract_office_text, output) {
+        Ok(texts) => {
+            for (location, text) in texts {
+                if find_fn(&text, search_filter) {
+                    let fields = HitFields { matched: Some(text.trim().to_string()), ..HitFields::default() };
+                    output.output_hit(format!("{} :: {} :: {}", file_name, location, text.trim()).as_str(), fields);
+                }
+            }
+        }
+        Err(e) => {
+            output.err_output(format!("Could not extract text from '{}': {}", file_name, e).as_str());
+        }
+    }
+}
+
+/// Extracts the located text of Word, Excel, PowerPoint and OpenDocument files based on the file extension.
+pub(crate) fn extract_office_text(path: &Path) -> Result<Vec<LocatedText>, String> {
+    let extension = path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    match extension.as_str() {
+        "docx" | "docm" => extract_docx(&mut archive),
+        "xlsx" | "xlsm" => extract_xlsx(&mut archive),
+        "pptx" | "pptm" => extract_pptx(&mut archive),
+        "odt" | "odp" => extract_odt(&mut archive),
+        "ods" => extract_ods(&mut archive),
+        _ => Err(format!("Unsupported office format '{}'", extension))
+    }
+}
+
+fn read_entry(archive: &mut OfficeArchive, name: &str) -> Result<String, String> {
+    let mut entry = archive.by_name(name).map_err(|e| format!("{}: {}", name, e))?;
+    let mut content = String::new();
+    entry.read_to_string(&mut content).map_err(|e| format!("{}: {}", name, e))?;
+    Ok(content)
+}
+
+fn parse_xml<'a>(name: &str, content: &'a str) -> Result<Document<'a>, String> {
+    Document::parse(content).map_err(|e| format!("{}: {}", name, e))
+}
+
+fn is_element(node: &Node, namespace: &str, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name && node.tag_name().namespace() == Some(namespace)
+}
+
+/// The descendants of the paragraph which do not belong to a paragraph nested in it, like the paragraphs of
+/// text boxes and footnotes, which are reported on their own.
+fn own_descendants<'a, 'input>(paragraph: Node<'a, 'input>, is_paragraph: impl Fn(&Node) -> bool)
+                               -> impl Iterator<Item=Node<'a, 'input>> {
+    paragraph.descendants().filter(move |n| n.ancestors().find(|a| is_paragraph(a)) == Some(paragraph))
+}
+
+/// Concatenates the text of the elements with the given name belonging to the paragraph.
+fn paragraph_text(paragraph: Node, namespace: &str, name: &str) -> String {
+    own_descendants(paragraph, |n| is_element(n, namespace, "p"))
+        .filter(|n| is_element(n, namespace, name))
+        .filter_map(|n| n.text())
+        .collect()
+}
+
+fn extract_docx(archive: &mut OfficeArchive) -> Result<Vec<LocatedText>, String> {
+    let name = "word/document.xml";
+    let content = read_entry(archive, name)?;
+    let document = parse_xml(name, &content)?;
+    let texts = document.descendants()
+        .filter(|n| is_element(n, WORD_NS, "p"))
+        .enumerate()
+        .map(|(i, p)| (format!("paragraph {}", i + 1), paragraph_text(p, WORD_NS, "t")))
+        .filter(|(_, text)| !text.is_empty())
+        .collect();
+    Ok(texts)
+}
+
+fn extract_pptx(archive: &mut OfficeArchive) -> Result<Vec<LocatedText>, String> {
+    let mut slides = archive.file_names()
+        .filter_map(|name| {
+            name.strip_prefix("ppt/slides/slide")
+                .and_then(|rest| rest.strip_suffix(".xml"))
+                .and_then(|number| number.parse::<usize>().ok())
+                .map(|number| (number, name.to_string()))
+        })
+        .collect::<Vec<(usize, String)>>();
+    slides.sort();
+    let mut texts = vec![];
+    for (number, name) in slides {
+        let content = read_entry(archive, &name)?;
+        let document = parse_xml(&name, &content)?;
+        for p in document.descendants().filter(|n| is_element(n, DRAWING_NS, "p")) {
+            let text = paragraph_text(p, DRAWING_NS, "t");
+            if !text.is_empty() {
+                texts.push((format!("slide {}", number), text));
+            }
+        }
+    }
+    Ok(texts)
+}
+
+fn extract_xlsx(archive: &mut OfficeArchive) -> Result<Vec<LocatedText>, String> {
+    let shared_strings = match read_entry(archive, "xl/sharedStrings.xml") {
+        Ok(content) => {
+            let document = parse_xml("xl/sharedStrings.xml", &content)?;
+            document.root_element().children()
+                .filter(|n| is_element(n, SPREADSHEET_NS, "si"))
+                .map(spreadsheet_text)
+                .collect()
+        }
+        // Workbooks with numbers or inline strings only have no shared strings
+        Err(_) => vec![]
+    };
+    let mut texts = vec![];
+    for (sheet_name, sheet_path) in xlsx_sheets(archive)? {
+        let content = read_entry(archive, &sheet_path)?;
+        let document = parse_xml(&sheet_path, &content)?;
+        for cell in document.descendants().filter(|n| is_element(n, SPREADSHEET_NS, "c")) {
+            let reference = cell.attribute("r").unwrap_or("?");
+            let value = cell.children().find(|n| is_element(n, SPREADSHEET_NS, "v")).and_then(|v| v.text());
+            let text = match cell.attribute("t") {
+                Some("s") => value
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .and_then(|i| shared_strings.get(i).cloned()),
+                Some("inlineStr") => cell.children()
+                    .find(|n| is_element(n, SPREADSHEET_NS, "is"))
+                    .map(spreadsheet_text),
+                _ => value.map(|v| v.to_string())
+            };
+            if let Some(text) = text.filter(|t| !t.is_empty()) {
+                texts.push((format!("{}!{}", sheet_name, reference), text));
+            }
+        }
+    }
+    Ok(texts)
+}
+
+/// Text of a shared or inline string, which might be split into rich text runs.
+/// Phonetic hints are ignored.
+fn spreadsheet_text(node: Node) -> String {
+    node.descendants()
+        .filter(|n| is_element(n, SPREADSHEET_NS, "t"))
+        .filter(|t| !t.ancestors().any(|a| is_element(&a, SPREADSHEET_NS, "rPh")))
+        .filter_map(|t| t.text())
+        .collect()
+}
+
+/// Resolves the sheet names of the workbook to the paths of the worksheets in the archive.
+fn xlsx_sheets(archive: &mut OfficeArchive) -> Result<Vec<(String, String)>, String> {
+    let rels_name = "xl/_rels/workbook.xml.rels";
+    let rels_content = read_entry(archive, rels_name)?;
+    let rels = parse_xml(rels_name, &rels_content)?;
+    let targets = rels.descendants()
+        .filter(|n| is_element(n, PACKAGE_RELATIONSHIPS_NS, "Relationship"))
+        .filter_map(|n| Some((n.attribute("Id")?.to_string(), n.attribute("Target")?.to_string())))
+        .collect::<HashMap<String, String>>();
+    let workbook_name = "xl/workbook.xml";
+    let workbook_content = read_entry(archive, workbook_name)?;
+    let workbook = parse_xml(workbook_name, &workbook_content)?;
+    let sheets = workbook.descendants()
+        .filter(|n| is_element(n, SPREADSHEET_NS, "sheet"))
+        .filter_map(|n| {
+            let name = n.attribute("name")?;
+            let target = targets.get(n.attribute((RELATIONSHIPS_NS, "id"))?)?;
+            let path = match target.strip_prefix('/') {
+                Some(absolute) => absolute.to_string(),
+                None => format!("xl/{}", target)
+            };
+            Some((name.to_string(), path))
+        })
+        .collect();
+    Ok(sheets)
+}
+
+fn extract_odt(archive: &mut OfficeArchive) -> Result<Vec<LocatedText>, String> {
+    let name = "content.xml";
+    let content = read_entry(archive, name)?;
+    let document = parse_xml(name, &content)?;
+    let texts = document.descendants()
+        .filter(is_odf_paragraph)
+        .enumerate()
+        .map(|(i, p)| (format!("paragraph {}", i + 1), odf_text(p)))
+        .filter(|(_, text)| !text.is_empty())
+        .collect();
+    Ok(texts)
+}
+
+fn extract_ods(archive: &mut OfficeArchive) -> Result<Vec<LocatedText>, String> {
+    let name = "content.xml";
+    let content = read_entry(archive, name)?;
+    let document = parse_xml(name, &content)?;
+    let mut texts = vec![];
+    for table in document.descendants().filter(|n| is_element(n, ODF_TABLE_NS, "table")) {
+        let sheet_name = table.attribute((ODF_TABLE_NS, "name")).unwrap_or("?");
+        let mut row_number = 1;
+        for row in table.descendants().filter(|n| is_element(n, ODF_TABLE_NS, "table-row")) {
+            let mut column = 0;
+            for cell in row.children().filter(|n| is_element(n, ODF_TABLE_NS, "table-cell")
+                || is_element(n, ODF_TABLE_NS, "covered-table-cell")) {
+                let text = cell.children()
+                    .filter(|n| is_element(n, ODF_TEXT_NS, "p"))
+                    .map(odf_text)
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                if !text.is_empty() {
+                    texts.push((format!("{}!{}{}", sheet_name, column_name(column), row_number), text));
+                }
+                column += repeat_count(cell, "number-columns-repeated");
+            }
+            row_number += repeat_count(row, "number-rows-repeated");
+        }
+    }
+    Ok(texts)
+}
+
+fn is_odf_paragraph(node: &Node) -> bool {
+    is_element(node, ODF_TEXT_NS, "p") || is_element(node, ODF_TEXT_NS, "h")
+}
+
+fn repeat_count(node: Node, attribute: &str) -> usize {
+    node.attribute((ODF_TABLE_NS, attribute))
+        .and_then(|r| r.parse::<usize>().ok())
+        .unwrap_or(1)
+}
+
+/// Text of an OpenDocument paragraph or heading, expanding the space and tab elements.
+fn odf_text(node: Node) -> String {
+    let mut text = String::new();
+    for n in own_descendants(node, is_odf_paragraph) {
+        if n.is_text() {
+            text += n.text().unwrap_or_default();
+        } else if is_element(&n, ODF_TEXT_NS, "s") {
+            let count = n.attribute((ODF_TEXT_NS, "c")).and_then(|c| c.parse::<usize>().ok()).unwrap_or(1);
+            text += &" ".repeat(count);
+        } else if is_element(&n, ODF_TEXT_NS, "tab") {
+            text += "\t";
+        } else if is_element(&n, ODF_TEXT_NS, "line-break") {
+            text += "\n";
+        }
+    }
+    text
+}
+
+/// Converts a zero based column index to the spreadsheet column name, e.g. 0 to "A" and 27 to "AB".
+fn column_name(index: usize) -> String {
+    let mut name = vec![];
+    let mut n = index + 1;
+    while n > 0 {
+        let remainder = (n - 1) % 26;
+        name.push((b'A' + remainder as u8) as char);
+        n = (n - 1) / 26;
+    }
+    name.iter().rev().collect()
+}
@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use fancy_regex::Regex;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use crate::cli::Cli;
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+/// Keys of the document information dictionary which are reported.
+const INFO_KEYS: [&str; 6] = ["Title", "Author", "Subject", "Keywords", "Creator", "Producer"];
+
+pub(crate) fn process_pdf_metadata_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    match &cli.search_expression {
+        Some(search_expression) => {
+            if needs_regex(cli) {
+                let re = build_regex(cli, search_expression, true);
+                process_pdf_metadata_generic(path, Some(&re), find_regex, output);
+            } else {
+                process_pdf_metadata_generic(path, Some(search_expression), find_simple, output);
+            }
+        }
+        None => {
+            process_pdf_metadata_generic::<Regex>(path, None, find_regex, output);
+        }
+    }
+}
+
+/// Prints every metadata field matching the filter as "file :: field :: value".
+/// All fields are printed if there is no filter.
+fn process_pdf_metadata_generic<T>(path: PathBuf, search_filter: Option<&T>,
+                                   find_fn: fn(&str, &T) -> bool,
+                                   output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().expect("Could not extract file name from path");
+    match Document::load(&path) {
+        Ok(document) => {
+            for (field, value) in extract_metadata(&document) {
+                let matches = match search_filter {
+                    Some(filter) => find_fn(&value, filter),
+                    None => true
+                };
+                if matches {
+                    let fields = HitFields { matched: Some(value.clone()), ..HitFields::default() };
+                    output.output_hit(format!("{} :: {} :: {}", file_name, field, value).as_str(), fields);
+                }
+            }
+        }
+        Err(e) => {
+            output.err_output(format!("Could not read pdf '{}': {:?}", file_name, e).as_str());
+        }
+    }
+}
+
+/// Collects the document information dictionary, the XMP metadata and the outline titles as field / value pairs.
+fn extract_metadata(document: &Document) -> Vec<(String, String)> {
+    let mut fields = vec![];
+    if let Some(info) = document.trailer.get(b"Info").ok().and_then(|o| resolve_dict(document, o)) {
+        for key in INFO_KEYS {
+            if let Some(value) = info.get(key.as_bytes()).ok().and_then(|o| resolve_text(document, o)) {
+                fields.push((format!("Info.{}", key), value));
+            }
+        }
+    }
+    if let Ok(catalog) = document.catalog() {
+        if let Some(metadata) = catalog.get(b"Metadata").ok().and_then(|o| resolve(document, o)).and_then(|o| o.as_stream().ok()) {
+            let content = metadata.decompressed_content().unwrap_or_else(|_| metadata.content.clone());
+            for (key, value) in extract_xmp(&String::from_utf8_lossy(&content)) {
+                fields.push((format!("XMP.{}", key), value));
+            }
+        }
+        if let Some(outlines) = catalog.get(b"Outlines").ok().and_then(|o| resolve_dict(document, o)) {
+            let mut visited = HashSet::new();
+            collect_outline(document, outlines.get(b"First").ok(), 1, &mut visited, &mut fields);
+        }
+    }
+    fields
+}
+
+fn collect_outline(document: &Document, first: Option<&Object>, level: usize,
+                   visited: &mut HashSet<ObjectId>, fields: &mut Vec<(String, String)>) {
+    let mut current = first.and_then(|o| o.as_reference().ok());
+    while let Some(id) = current {
+        // Broken files may contain cycles in the outline tree
+        if !visited.insert(id) {
+            break;
+        }
+        match document.get_dictionary(id) {
+            Ok(item) => {
+                if let Some(title) = item.get(b"Title").ok().and_then(|o| resolve_text(document, o)) {
+                    fields.push((format!("Outline.{}", level), title));
+                }
+                collect_outline(document, item.get(b"First").ok(), level + 1, visited, fields);
+                current = item.get(b"Next").ok().and_then(|o| o.as_reference().ok());
+            }
+            Err(_) => break
+        }
+    }
+}
+
+/// Extracts the Dublin Core, XMP basic and PDF schema properties from the XMP packet.
+/// Properties can either be elements (with rdf:Alt, rdf:Seq or rdf:Bag containers) or attributes of rdf:Description.
+fn extract_xmp(xmp: &str) -> Vec<(String, String)> {
+    let element_re = Regex::new(r"(?s)<((?:dc|xmp|pdf|pdfx|photoshop):\w+)(?:\s[^>]*)?>(.*?)</\1>").unwrap();
+    let attribute_re = Regex::new(r#"\s((?:dc|xmp|pdf|pdfx|photoshop):\w+)="([^"]*)""#).unwrap();
+    let tag_re = Regex::new(r"<[^>]+>").unwrap();
+    let mut fields = vec![];
+    for captures in element_re.captures_iter(xmp).flatten() {
+        let text = tag_re.replace_all(&captures[2], " ");
+        let value = decode_xml_entities(&text.split_whitespace().collect::<Vec<&str>>().join(" "));
+        if !value.is_empty() {
+            fields.push((captures[1].to_string(), value));
+        }
+    }
+    for captures in attribute_re.captures_iter(xmp).flatten() {
+        fields.push((captures[1].to_string(), decode_xml_entities(&captures[2])));
+    }
+    fields
+}
+
+fn decode_xml_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn resolve<'a>(document: &'a Document, object: &'a Object) -> Option<&'a Object> {
+    match object {
+        Object::Reference(id) => document.get_object(*id).ok(),
+        _ => Some(object)
+    }
+}
+
+fn resolve_dict<'a>(document: &'a Document, object: &'a Object) -> Option<&'a Dictionary> {
+    resolve(document, object).and_then(|o| o.as_dict().ok())
+}
+
+/// Decodes a PDF text string, which is either UTF-16BE with a byte order mark or PDFDocEncoding.
+fn resolve_text(document: &Document, object: &Object) -> Option<String> {
+    match resolve(document, object) {
+        Some(Object::String(bytes, _)) => {
+            let text = if bytes.starts_with(&[0xFE, 0xFF]) {
+                let utf16 = bytes[2..].chunks(2)
+                    .filter(|c| c.len() == 2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect::<Vec<u16>>();
+                String::from_utf16_lossy(&utf16)
+            } else {
+                bytes.iter().map(|b| *b as char).collect()
+            };
+            Some(text.trim().to_string()).filter(|t| !t.is_empty())
+        }
+        _ => None
+    }
+}
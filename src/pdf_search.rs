@@ -1,31 +1,151 @@
 extern crate lopdf;
 
-use std::path::PathBuf;
-use pdf_extract::extract_text;
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use lopdf::Document;
+use pdf_extract::{MediaBox, OutputDev, OutputError, PlainTextOutput, Transform, output_doc};
+use crate::cli::Cli;
+use crate::result_printer::HitFields;
 use crate::OutputPrinter;
-use crate::finders::find_simple_pos;
+use crate::finders::{build_regex, find_regex_pos, find_simple_pos, needs_regex};
+use crate::text_cache::extract_cached;
 use unicode_segmentation::UnicodeSegmentation;
 
-pub(crate) fn process_pdf_simple_search(path: PathBuf, search_expression_option: &Option<String>,
-                                        output: &mut dyn OutputPrinter) {
-    let extracted = extract_text(path.clone());
+/// Number of graphemes shown before and after a match in the pdf hit.
+const CONTEXT_GRAPHEMES: usize = 40;
+
+pub(crate) fn process_pdf_simple_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    let search_expression = cli.search_expression.as_ref().unwrap();
+    if needs_regex(cli) {
+        let re = build_regex(cli, search_expression, true);
+        process_pdf_search_generic(path, cli, &re, find_regex_pos, output);
+    } else {
+        process_pdf_search_generic(path, cli, search_expression, find_simple_pos, output);
+    }
+}
+
+pub(crate) fn process_pdf_regex_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    let re = build_regex(cli, cli.search_expression.as_ref().unwrap(), false);
+    process_pdf_search_generic(path, cli, &re, find_regex_pos, output);
+}
+
+fn process_pdf_search_generic<T>(path: PathBuf, cli: &Cli, search_filter: &T,
+                                 find_fn: fn(&str, &T) -> Option<(usize, usize)>,
+                                 output: &mut dyn OutputPrinter) {
+    let extracted = extract_cached(cli, &path, extract_pdf_pages, output);
     let file_name = path.to_str().expect("Could not extract file name from path");
     match extracted {
-        Ok(content) => {
-            let search_expression = search_expression_option.as_ref().unwrap();
+        Ok(pages) => {
+            let content = pages.join("\n");
             let content_str = content.as_str();
-            let found = find_simple_pos(content_str, search_expression);
-            if found.is_some() {
-                let example_vec = UnicodeSegmentation::grapheme_indices(content_str, true)
-                    .collect::<Vec<(usize, &str)>>();
-                let example = &example_vec[..found.unwrap() + search_expression.len()].iter()
-                    .map(|x| x.1).collect::<String>();
-                output.output_with_stats(format!("{} :: {} :: @@{}@@", file_name, found.unwrap(), example)
-                    .as_str());
+            if let Some((start, end)) = find_fn(content_str, search_filter) {
+                let example = extract_context(content_str, start, end);
+                let fields = HitFields { matched: Some(content_str[start..end].to_string()), ..HitFields::default() };
+                output.output_hit(format!("{} :: {} :: @@{}@@", file_name, start, example).as_str(), fields);
             }
         }
         Err(e) => {
-            output.err_output(format!("Could not extract text from '{}': {:?}", file_name, e).as_str());
+            output.err_output(format!("Could not extract text from '{}': {}", file_name, e).as_str());
+        }
+    }
+}
+
+/// Extracts the match plus some surrounding graphemes with the whitespace collapsed,
+/// so that the hit fits on a single line.
+fn extract_context(content: &str, start: usize, end: usize) -> String {
+    let graphemes = UnicodeSegmentation::grapheme_indices(content, true)
+        .collect::<Vec<(usize, &str)>>();
+    let first = graphemes.iter().position(|(i, _)| *i >= start).unwrap_or(graphemes.len());
+    let last = graphemes.iter().position(|(i, _)| *i >= end).unwrap_or(graphemes.len());
+    let from = first.saturating_sub(CONTEXT_GRAPHEMES);
+    let to = (last + CONTEXT_GRAPHEMES).min(graphemes.len());
+    graphemes[from..to].iter()
+        .map(|x| x.1)
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Extracts the text of every page of the pdf.
+pub(crate) fn extract_pdf_pages(path: &Path) -> Result<Vec<String>, String> {
+    let document = Document::load(path).map_err(|e| format!("{:?}", e))?;
+    extract_document_pages(&document)
+}
+
+/// Extracts the pages of a pdf held in memory, e.g. an email attachment.
+pub(crate) fn extract_pdf_pages_from_bytes(bytes: &[u8]) -> Result<Vec<String>, String> {
+    let document = Document::load_mem(bytes).map_err(|e| format!("{:?}", e))?;
+    extract_document_pages(&document)
+}
+
+fn extract_document_pages(document: &Document) -> Result<Vec<String>, String> {
+    let pages = Rc::new(RefCell::new(vec![]));
+    let mut sink = PageSink { pages: pages.clone() };
+    {
+        let mut output = PagedTextOutput {
+            pages: pages.clone(),
+            inner: PlainTextOutput::new(&mut sink as &mut dyn Write),
+        };
+        output_doc(document, &mut output).map_err(|e| format!("{:?}", e))?;
+    }
+    let extracted = pages.borrow().iter()
+        .map(|page: &Vec<u8>| String::from_utf8_lossy(page).to_string())
+        .collect();
+    Ok(extracted)
+}
+
+/// Writer which appends the plain text output to the current page.
+struct PageSink {
+    pages: Rc<RefCell<Vec<Vec<u8>>>>,
+}
+
+impl Write for PageSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut pages = self.pages.borrow_mut();
+        if pages.is_empty() {
+            pages.push(vec![]);
         }
+        pages.last_mut().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Delegates to the plain text output of pdf-extract, starting a new page in the sink on every page break.
+struct PagedTextOutput<'a> {
+    pages: Rc<RefCell<Vec<Vec<u8>>>>,
+    inner: PlainTextOutput<&'a mut dyn Write>,
+}
+
+impl OutputDev for PagedTextOutput<'_> {
+    fn begin_page(&mut self, page_num: u32, media_box: &MediaBox, art_box: Option<(f64, f64, f64, f64)>) -> Result<(), OutputError> {
+        self.pages.borrow_mut().push(vec![]);
+        self.inner.begin_page(page_num, media_box, art_box)
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        self.inner.end_page()
+    }
+
+    fn output_character(&mut self, trm: &Transform, width: f64, spacing: f64, font_size: f64, char: &str) -> Result<(), OutputError> {
+        self.inner.output_character(trm, width, spacing, font_size, char)
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        self.inner.begin_word()
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        self.inner.end_word()
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        self.inner.end_line()
     }
-}
\ No newline at end of file
+}
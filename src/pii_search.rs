@@ -0,0 +1,311 @@
+use std::fs;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use csv::StringRecord;
+use fancy_regex::Regex;
+
+use crate::cli::{Cli, PiiType};
+use crate::csv_search::open_csv_reader;
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::io_ops::{glob_base, resolve_path, target_path};
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+/// Kind of personal data, candidate pattern and validation of the candidates.
+type DetectorDefinition = (PiiType, &'static str, fn(&str) -> bool);
+
+/// Candidate patterns by kind, checked further by a validation function. Earlier patterns take precedence
+/// over later ones matching the same text, e.g. the digits of an IBAN are not reported as a phone number.
+const DETECTORS: [DetectorDefinition; 8] = [
+    (PiiType::Email, r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b", always),
+    (PiiType::Iban, r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b", valid_iban),
+    (PiiType::CreditCard, r"\b\d(?:[ -]?\d){12,18}\b", valid_credit_card),
+    (PiiType::NationalId, r"\b\d{3}-\d{2}-\d{4}\b", valid_ssn),
+    (PiiType::NationalId, r"\b[A-CEGHJ-PR-TW-Z][A-CEGHJ-NPR-TW-Z] ?\d{2} ?\d{2} ?\d{2} ?[A-D]\b", valid_nino),
+    (PiiType::Ip, r"(?<![\d.])(?:\d{1,3}\.){3}\d{1,3}(?!\.?\d)", |v| v.parse::<Ipv4Addr>().is_ok()),
+    (PiiType::Ip, r"(?i)(?<![\w:])(?:[0-9a-f]{1,4}:|:){2,7}(?:[0-9a-f]{1,4}|:)(?![\w:])", |v| v.parse::<Ipv6Addr>().is_ok()),
+    (PiiType::Phone, r"(?<![\w+])(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\d{2,4}(?:[ .-]?\d{2,4}){1,4}(?!\w)", valid_phone),
+];
+
+/// Extensions of files which are read as csv, so findings are reported by row and column.
+const CSV_EXTENSIONS: [&str; 2] = ["csv", "tsv"];
+
+/// Number of bytes checked for zero bytes to tell binary from text content.
+const BINARY_CHECK_BYTES: usize = 8000;
+
+pub(crate) struct Detector {
+    pii_type: PiiType,
+    regex: Regex,
+    validate: fn(&str) -> bool,
+}
+
+pub(crate) fn process_pii_search(path: PathBuf, cli: &Cli, detectors: &[Detector], output: &mut dyn OutputPrinter) {
+    match &cli.search_expression {
+        Some(search_expression) => {
+            if needs_regex(cli) {
+                let re = build_regex(cli, search_expression, true);
+                process_pii_search_generic(path, cli, detectors, Some(&re), find_regex, output);
+            } else {
+                process_pii_search_generic(path, cli, detectors, Some(search_expression), find_simple, output);
+            }
+        }
+        None => {
+            process_pii_search_generic::<Regex>(path, cli, detectors, None, find_regex, output);
+        }
+    }
+}
+
+/// Prints "file :: line :: kind :: value" for the personal data found in text files and
+/// "file :: row :: column :: kind :: value" for csv files, with rows numbered from 1 as in csv mode.
+/// Only findings whose value matches the search expression are reported if there is one.
+/// With --redacted-dir a copy of every file with findings is written with the findings replaced by their kind,
+/// unless --redact replaces them instead.
+fn process_pii_search_generic<T>(path: PathBuf, cli: &Cli, detectors: &[Detector], search_filter: Option<&T>,
+                                 find_fn: fn(&str, &T) -> bool,
+                                 output: &mut dyn OutputPrinter) {
+    if path.is_dir() {
+        return;
+    }
+    let file_name = path.to_str().unwrap();
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str());
+            return;
+        }
+    };
+    if bytes[..bytes.len().min(BINARY_CHECK_BYTES)].contains(&0) {
+        return;
+    }
+    let selected = |value: &str| search_filter.map(|filter| find_fn(value, filter)).unwrap_or(true);
+    let text = String::from_utf8_lossy(&bytes);
+    let mut findings = vec![];
+    let has_findings = if is_csv(&path) {
+        search_csv(&path, cli, detectors, &selected, output)
+    } else {
+        findings = find_in_lines(&text, detectors, &selected);
+        for (linenumber, pii_type, span) in &findings {
+            let value = &text[span.clone()];
+            let fields = HitFields {
+                line: Some(*linenumber as u64 + 1),
+                matched: Some(value.to_string()),
+                ..HitFields::default()
+            };
+            output.output_hit(format!("{} :: {} :: {} :: {}", file_name, linenumber,
+                                      type_name(*pii_type), value).as_str(), fields);
+        }
+        !findings.is_empty()
+    };
+    if let (Some(redacted_dir), None, true) = (&cli.redacted_dir, &cli.redact, has_findings) {
+        let resolved = resolve_path(&path).and_then(|p| resolve_path(&glob_base(&cli.glob_pattern)).map(|b| (p, b)));
+        let (resolved_path, base) = match resolved {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str());
+                return;
+            }
+        };
+        let target = target_path(Path::new(redacted_dir), &base, &resolved_path);
+        if findings.is_empty() {
+            // Csv files are redacted line by line like text files, so the quotes and delimiters stay as they are
+            findings = find_in_lines(&text, detectors, &selected);
+        }
+        match write_redacted_copy(&bytes, &findings, &resolved_path, &target) {
+            Ok(()) => output.output(format!("{} :: redacted copy :: {}", file_name, target.display()).as_str()),
+            Err(e) => output.err_output(format!("{} :: Could not write redacted copy {}: {}",
+                                                file_name, target.display(), e).as_str())
+        }
+    }
+}
+
+/// Reports the findings of the csv file field by field and returns whether there are any. Fields which are not
+/// UTF-8 are decoded lossily and rows may have any number of fields, as in csv mode.
+fn search_csv(path: &Path, cli: &Cli, detectors: &[Detector], selected: &dyn Fn(&str) -> bool,
+              output: &mut dyn OutputPrinter) -> bool {
+    let file_name = path.to_str().unwrap();
+    let mut reader = match open_csv_reader(path, cli) {
+        Ok(reader) => reader,
+        Err(e) => {
+            output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str());
+            return false;
+        }
+    };
+    let headers = match reader.byte_headers() {
+        Ok(headers) => StringRecord::from_byte_record_lossy(headers.clone()),
+        Err(e) => {
+            output.err_output(format!("{} :: Could not read header: {}", file_name, e).as_str());
+            return false;
+        }
+    };
+    let mut has_findings = false;
+    for (index, record) in reader.byte_records().enumerate() {
+        let row = index + 1;
+        let record = match record {
+            Ok(record) => StringRecord::from_byte_record_lossy(record),
+            Err(e) => {
+                output.err_output(format!("{} :: {} :: {}", file_name, row, e).as_str());
+                continue;
+            }
+        };
+        for (column, field) in record.iter().enumerate() {
+            for (pii_type, span) in find_pii(field, detectors) {
+                let value = &field[span];
+                if !selected(value) {
+                    continue;
+                }
+                let fields = HitFields { matched: Some(value.to_string()), ..HitFields::default() };
+                output.output_hit(format!("{} :: {} :: {} :: {} :: {}", file_name, row,
+                                          headers.get(column).unwrap_or(""), type_name(pii_type),
+                                          value).as_str(), fields);
+                has_findings = true;
+            }
+        }
+    }
+    has_findings
+}
+
+/// The selected findings of the text line by line, with the line number and the position in the text.
+fn find_in_lines(text: &str, detectors: &[Detector], selected: &dyn Fn(&str) -> bool)
+                 -> Vec<(usize, PiiType, Range<usize>)> {
+    let mut findings = vec![];
+    let mut offset = 0;
+    for (linenumber, line_with_ending) in text.split_inclusive('\n').enumerate() {
+        let line = line_with_ending.trim_end_matches(['\r', '\n']);
+        for (pii_type, span) in find_pii(line, detectors) {
+            if selected(&line[span.clone()]) {
+                findings.push((linenumber, pii_type, offset + span.start..offset + span.end));
+            }
+        }
+        offset += line_with_ending.len();
+    }
+    findings
+}
+
+pub(crate) fn build_detectors(pii_types: &[PiiType]) -> Vec<Detector> {
+    DETECTORS.iter()
+        .filter(|(pii_type, _, _)| pii_types.is_empty() || pii_types.contains(pii_type))
+        .map(|(pii_type, regex, validate)| Detector {
+            pii_type: *pii_type,
+            regex: Regex::new(regex).expect("Invalid pii pattern"),
+            validate: *validate,
+        })
+        .collect()
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .map(|e| CSV_EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+pub(crate) fn type_name(pii_type: PiiType) -> &'static str {
+    match pii_type {
+        PiiType::Email => "email",
+        PiiType::Phone => "phone",
+        PiiType::Iban => "iban",
+        PiiType::CreditCard => "credit-card",
+        PiiType::NationalId => "national-id",
+        PiiType::Ip => "ip",
+    }
+}
+
+/// Returns the valid findings in the order they appear. Text matched by one detector is not
+/// considered by the following ones.
+pub(crate) fn find_pii(text: &str, detectors: &[Detector]) -> Vec<(PiiType, Range<usize>)> {
+    let mut findings: Vec<(usize, usize, PiiType)> = vec![];
+    for detector in detectors {
+        for candidate in detector.regex.find_iter(text).flatten() {
+            let overlaps = findings.iter().any(|(start, end, _)| candidate.start() < *end && *start < candidate.end());
+            if !overlaps && (detector.validate)(candidate.as_str()) {
+                findings.push((candidate.start(), candidate.end(), detector.pii_type));
+            }
+        }
+    }
+    findings.sort_by_key(|(start, _, _)| *start);
+    findings.into_iter().map(|(start, end, pii_type)| (pii_type, start..end)).collect()
+}
+
+fn always(_: &str) -> bool {
+    true
+}
+
+/// Checks the length and the ISO 7064 mod 97 checksum.
+fn valid_iban(value: &str) -> bool {
+    let compact = value.replace(' ', "");
+    if !(15..=34).contains(&compact.len()) {
+        return false;
+    }
+    let rearranged = compact[4..].chars().chain(compact[..4].chars());
+    let mut remainder = 0u32;
+    for c in rearranged {
+        remainder = match c.to_digit(36) {
+            Some(n) if n < 10 => (remainder * 10 + n) % 97,
+            Some(n) => (remainder * 100 + n) % 97,
+            None => return false
+        };
+    }
+    remainder == 1
+}
+
+/// Checks the length, the major industry identifier of payment cards and the Luhn checksum.
+fn valid_credit_card(value: &str) -> bool {
+    let digits = value.chars().filter_map(|c| c.to_digit(10)).collect::<Vec<u32>>();
+    if !(13..=19).contains(&digits.len()) || !(2..=6).contains(&digits[0]) {
+        return false;
+    }
+    let sum: u32 = digits.iter().rev().enumerate()
+        .map(|(i, d)| if i % 2 == 1 { if d * 2 > 9 { d * 2 - 9 } else { d * 2 } } else { *d })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// US social security numbers, excluding the area, group and serial numbers which are never assigned.
+fn valid_ssn(value: &str) -> bool {
+    let parts = value.split('-').collect::<Vec<&str>>();
+    let area = parts[0].parse::<u32>().unwrap_or(0);
+    area != 0 && area != 666 && area < 900 && parts[1] != "00" && parts[2] != "0000"
+}
+
+/// UK national insurance numbers, excluding the prefixes which are never assigned.
+fn valid_nino(value: &str) -> bool {
+    !["BG", "GB", "NK", "KN", "TN", "NT", "ZZ"].contains(&&value[..2])
+}
+
+/// Phone numbers have 8 to 15 digits and start with a country code or area code in brackets or are grouped.
+/// Dates and social security numbers are excluded.
+fn valid_phone(value: &str) -> bool {
+    let digits = value.chars().filter(|c| c.is_ascii_digit()).count();
+    let groups = value.split(|c: char| " .-()".contains(c)).filter(|g| !g.is_empty()).collect::<Vec<&str>>();
+    let looks_like_date = groups.len() == 3
+        && groups.iter().any(|g| g.len() == 4)
+        && groups.iter().filter(|g| g.len() <= 2).count() == 2;
+    let looks_like_ssn = groups.iter().map(|g| g.len()).eq([3, 2, 4]);
+    (8..=15).contains(&digits)
+        && (value.starts_with('+') || value.starts_with('(') || groups.len() >= 3)
+        && !looks_like_date
+        && !looks_like_ssn
+}
+
+/// Replaces the findings, given by their position, with their kind. Content which is not valid UTF-8 is refused,
+/// as it cannot be rewritten without changing it.
+fn write_redacted_copy(bytes: &[u8], findings: &[(usize, PiiType, Range<usize>)], source: &Path,
+                       target: &Path) -> Result<(), String> {
+    let text = std::str::from_utf8(bytes).map_err(|_| "the file is not valid UTF-8".to_string())?;
+    if resolve_path(target).map(|t| t == source).unwrap_or(true) {
+        return Err("the copy would replace the file".to_string());
+    }
+    let mut redacted = String::with_capacity(text.len());
+    let mut position = 0;
+    for (_, pii_type, span) in findings {
+        redacted.push_str(&text[position..span.start]);
+        redacted.push_str(&format!("[{}]", type_name(*pii_type).to_uppercase()));
+        position = span.end;
+    }
+    redacted.push_str(&text[position..]);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(target, redacted).map_err(|e| e.to_string())
+}
@@ -0,0 +1,584 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use csv::StringRecord;
+use fancy_regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::cli::Cli;
+use crate::csv_search::open_csv_reader;
+use crate::io_ops::read_lines;
+use crate::json_path_search::is_json_lines;
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+#[derive(Debug, Clone, Copy)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A condition of the query language, e.g. `age > 30 AND country = 'UK'`.
+enum Condition {
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+    Compare(String, Operator, Value),
+    /// LIKE patterns and regular expressions, both matched against the textual form of the field.
+    Matches(String, Regex),
+    In(String, Vec<Value>),
+    IsNull(String),
+}
+
+/// Filter expression evaluated against every record of csv, json and json lines files in query mode.
+pub(crate) struct Query {
+    condition: Condition,
+    ignore_case: bool,
+}
+
+/// Parses the query of the search expression or exits if it is not valid.
+pub(crate) fn build_query(cli: &Cli) -> Query {
+    let search_expression = cli.search_expression.as_ref().unwrap();
+    match parse_query(search_expression, cli.ignore_case) {
+        Ok(query) => query,
+        Err(e) => {
+            eprintln!("Invalid query '{}': {}", search_expression, e);
+            process::exit(0x0001);
+        }
+    }
+}
+
+/// Prints "file :: row :: record" for every record matching the query, with the record as json.
+/// Rows are numbered from 1: csv rows without the header, json lines by their line and the elements of json arrays.
+/// Csv fields which are not UTF-8 are decoded lossily.
+pub(crate) fn process_query_search(path: PathBuf, cli: &Cli, query: &Query, output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().unwrap();
+    if cli.json_lines || is_json_lines(&path) {
+        process_json_lines(file_name, &path, query, output);
+    } else if path.extension().map(|e| e.to_string_lossy().to_lowercase() == "json").unwrap_or(false) {
+        process_json(file_name, &path, query, output);
+    } else {
+        process_csv(file_name, &path, cli, query, output);
+    }
+}
+
+fn print_if_matches(file_name: &str, row: usize, record: &Value, query: &Query, output: &mut dyn OutputPrinter) {
+    if query.matches(record) {
+        let fields = HitFields { line: Some(row as u64), matched: Some(record.to_string()), ..HitFields::default() };
+        output.output_hit(format!("{} :: {} :: {}", file_name, row, record).as_str(), fields);
+    }
+}
+
+fn process_csv(file_name: &str, path: &Path, cli: &Cli, query: &Query, output: &mut dyn OutputPrinter) {
+    let mut reader = match open_csv_reader(path, cli) {
+        Ok(reader) => reader,
+        Err(e) => {
+            output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str());
+            return;
+        }
+    };
+    let headers = match reader.byte_headers() {
+        Ok(headers) => StringRecord::from_byte_record_lossy(headers.clone()),
+        Err(e) => {
+            output.err_output(format!("{} :: Could not read header: {}", file_name, e).as_str());
+            return;
+        }
+    };
+    for (index, record) in reader.byte_records().enumerate() {
+        let row = index + 1;
+        match record {
+            Ok(record) => {
+                let record = StringRecord::from_byte_record_lossy(record);
+                // Fields stay text, they are converted when compared with a number or boolean
+                let fields = headers.iter().zip(record.iter())
+                    .map(|(header, field)| {
+                        let value = if field.trim().is_empty() { Value::Null } else { Value::String(field.to_string()) };
+                        (header.trim().to_string(), value)
+                    })
+                    .collect::<Map<String, Value>>();
+                print_if_matches(file_name, row, &Value::Object(fields), query, output);
+            }
+            Err(e) => {
+                output.err_output(format!("{} :: {} :: {}", file_name, row, e).as_str());
+            }
+        }
+    }
+}
+
+fn process_json_lines(file_name: &str, path: &Path, query: &Query, output: &mut dyn OutputPrinter) {
+    let lines = match read_lines(path) {
+        Ok(lines) => lines,
+        Err(e) => {
+            output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str());
+            return;
+        }
+    };
+    for (index, line) in lines.enumerate() {
+        let linenumber = index + 1;
+        match line {
+            Ok(s) => {
+                if s.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(&s) {
+                    Ok(record) => print_if_matches(file_name, linenumber, &record, query, output),
+                    Err(e) => {
+                        output.err_output(format!("{} :: {} :: Malformed json: {}", file_name, linenumber, e).as_str());
+                    }
+                }
+            }
+            Err(e) => {
+                output.err_output(format!("{} :: {} :: {}", file_name, linenumber, e).as_str());
+                if e.kind() != std::io::ErrorKind::InvalidData {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Json files hold either an array of records or a single record.
+fn process_json(file_name: &str, path: &Path, query: &Query, output: &mut dyn OutputPrinter) {
+    let document = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str::<Value>(&content).map_err(|e| e.to_string()));
+    match document {
+        Ok(Value::Array(records)) => {
+            for (index, record) in records.iter().enumerate() {
+                print_if_matches(file_name, index + 1, record, query, output);
+            }
+        }
+        Ok(record) => print_if_matches(file_name, 1, &record, query, output),
+        Err(e) => output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str())
+    }
+}
+
+impl Query {
+    fn matches(&self, record: &Value) -> bool {
+        self.evaluate(&self.condition, record)
+    }
+
+    /// Comparisons with missing or null fields are false, so `NOT` of such a comparison is true.
+    fn evaluate(&self, condition: &Condition, record: &Value) -> bool {
+        match condition {
+            Condition::And(left, right) => self.evaluate(left, record) && self.evaluate(right, record),
+            Condition::Or(left, right) => self.evaluate(left, record) || self.evaluate(right, record),
+            Condition::Not(inner) => !self.evaluate(inner, record),
+            Condition::Compare(name, operator, literal) => field(record, name)
+                .and_then(|value| self.compare(value, literal))
+                .map(|ordering| operator.accepts(ordering))
+                .unwrap_or(false),
+            Condition::Matches(name, re) => field(record, name)
+                .map(|value| matches!(re.is_match(&text_of(value)), Ok(true)))
+                .unwrap_or(false),
+            Condition::In(name, literals) => field(record, name)
+                .map(|value| literals.iter().any(|literal| self.compare(value, literal) == Some(Ordering::Equal)))
+                .unwrap_or(false),
+            Condition::IsNull(name) => field(record, name).is_none(),
+        }
+    }
+
+    /// Compares the field with the literal, converting the field to the type of the literal.
+    /// Returns None if the field cannot be converted.
+    fn compare(&self, value: &Value, literal: &Value) -> Option<Ordering> {
+        match literal {
+            Value::Number(n) => {
+                let number = match value {
+                    Value::Number(v) => v.as_f64(),
+                    Value::String(s) => s.trim().parse::<f64>().ok(),
+                    _ => None
+                }?;
+                number.partial_cmp(&n.as_f64()?)
+            }
+            Value::Bool(b) => {
+                let boolean = match value {
+                    Value::Bool(v) => Some(*v),
+                    Value::String(s) => s.trim().to_lowercase().parse::<bool>().ok(),
+                    _ => None
+                }?;
+                Some(boolean.cmp(b))
+            }
+            Value::String(s) => {
+                let text = text_of(value);
+                if self.ignore_case {
+                    Some(text.to_lowercase().cmp(&s.to_lowercase()))
+                } else {
+                    Some(text.as_str().cmp(s.as_str()))
+                }
+            }
+            _ => None
+        }
+    }
+}
+
+impl Operator {
+    fn accepts(&self, ordering: Ordering) -> bool {
+        match self {
+            Operator::Eq => ordering == Ordering::Equal,
+            Operator::Ne => ordering != Ordering::Equal,
+            Operator::Lt => ordering == Ordering::Less,
+            Operator::Le => ordering != Ordering::Greater,
+            Operator::Gt => ordering == Ordering::Greater,
+            Operator::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// Looks up the field by its full name first and then as a dotted path into nested objects and arrays,
+/// e.g. `user.address.city` or `tags.0`. Null values are treated as missing.
+fn field<'a>(record: &'a Value, name: &str) -> Option<&'a Value> {
+    let value = match record.get(name) {
+        Some(value) => Some(value),
+        None => name.split('.').try_fold(record, |current, key| match current {
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => current.get(key)
+        })
+    };
+    value.filter(|v| !v.is_null())
+}
+
+fn text_of(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Text(String),
+    Number(String),
+    Symbol(String),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars = expression.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '\'' || c == '"' || c == '`' {
+            // Single quotes delimit text, double quotes and backticks field names. Doubled quotes are escapes.
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(format!("unterminated {} quote", c)),
+                    Some(q) if *q == c && chars.get(i + 1) == Some(&c) => {
+                        text.push(c);
+                        i += 2;
+                    }
+                    Some(q) if *q == c => {
+                        i += 1;
+                        break;
+                    }
+                    Some(other) => {
+                        text.push(*other);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push(if c == '\'' { Token::Text(text) } else { Token::Identifier(text) });
+        } else if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).map(|n| n.is_ascii_digit()).unwrap_or(false)) {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.'
+                || ((chars[i] == '-' || chars[i] == '+') && matches!(chars[i - 1], 'e' | 'E'))) {
+                i += 1;
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.' | '$')) {
+                i += 1;
+            }
+            tokens.push(Token::Identifier(chars[start..i].iter().collect()));
+        } else {
+            let two = chars[i..chars.len().min(i + 2)].iter().collect::<String>();
+            if ["<=", ">=", "!=", "<>", "=="].contains(&two.as_str()) {
+                tokens.push(Token::Symbol(two));
+                i += 2;
+            } else if "=<>~(),".contains(c) {
+                tokens.push(Token::Symbol(c.to_string()));
+                i += 1;
+            } else {
+                return Err(format!("unexpected character '{}'", c));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser with the precedence OR < AND < NOT < comparison.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    ignore_case: bool,
+}
+
+fn parse_query(expression: &str, ignore_case: bool) -> Result<Query, String> {
+    let mut parser = Parser { tokens: tokenize(expression)?, position: 0, ignore_case };
+    let condition = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("unexpected {:?}", token));
+    }
+    Ok(Query { condition, ignore_case })
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Identifier(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.accept_keyword(keyword) { Ok(()) } else { Err(format!("expected {}", keyword)) }
+    }
+
+    fn accept_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if s == symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn parse_or(&mut self) -> Result<Condition, String> {
+        let mut condition = self.parse_and()?;
+        while self.accept_keyword("OR") {
+            condition = Condition::Or(Box::new(condition), Box::new(self.parse_and()?));
+        }
+        Ok(condition)
+    }
+
+    fn parse_and(&mut self) -> Result<Condition, String> {
+        let mut condition = self.parse_not()?;
+        while self.accept_keyword("AND") {
+            condition = Condition::And(Box::new(condition), Box::new(self.parse_not()?));
+        }
+        Ok(condition)
+    }
+
+    fn parse_not(&mut self) -> Result<Condition, String> {
+        if self.accept_keyword("NOT") {
+            return Ok(Condition::Not(Box::new(self.parse_not()?)));
+        }
+        if self.accept_symbol("(") {
+            let condition = self.parse_or()?;
+            if !self.accept_symbol(")") {
+                return Err("expected )".to_string());
+            }
+            return Ok(condition);
+        }
+        self.parse_predicate()
+    }
+
+    /// Parses `field op literal`, `field [NOT] LIKE 'pattern'`, `field [NOT] REGEXP 'regex'`, `field ~ 'regex'`,
+    /// `field [NOT] IN (literal, ...)` and `field IS [NOT] NULL`.
+    fn parse_predicate(&mut self) -> Result<Condition, String> {
+        let name = match self.next() {
+            Some(Token::Identifier(name)) => name,
+            Some(token) => return Err(format!("expected a field name, found {:?}", token)),
+            None => return Err("expected a field name".to_string())
+        };
+        if self.accept_keyword("IS") {
+            let negated = self.accept_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(negate(Condition::IsNull(name), negated));
+        }
+        let negated = self.accept_keyword("NOT");
+        if self.accept_keyword("LIKE") {
+            let pattern = self.parse_text()?;
+            return Ok(negate(Condition::Matches(name, self.build_regex(&like_to_regex(&pattern))?), negated));
+        }
+        if self.accept_keyword("REGEXP") {
+            let pattern = self.parse_text()?;
+            return Ok(negate(Condition::Matches(name, self.build_regex(&pattern)?), negated));
+        }
+        if self.accept_keyword("IN") {
+            if !self.accept_symbol("(") {
+                return Err("expected ( after IN".to_string());
+            }
+            let mut literals = vec![self.parse_literal()?];
+            while self.accept_symbol(",") {
+                literals.push(self.parse_literal()?);
+            }
+            if !self.accept_symbol(")") {
+                return Err("expected ) after the IN list".to_string());
+            }
+            return Ok(negate(Condition::In(name, literals), negated));
+        }
+        if negated {
+            return Err("expected LIKE, REGEXP or IN after NOT".to_string());
+        }
+        let operator = match self.next() {
+            Some(Token::Symbol(s)) => match s.as_str() {
+                "=" | "==" => Operator::Eq,
+                "!=" | "<>" => Operator::Ne,
+                "<" => Operator::Lt,
+                "<=" => Operator::Le,
+                ">" => Operator::Gt,
+                ">=" => Operator::Ge,
+                "~" => {
+                    let pattern = self.parse_text()?;
+                    return Ok(Condition::Matches(name, self.build_regex(&pattern)?));
+                }
+                _ => return Err(format!("unexpected '{}' after {}", s, name))
+            },
+            _ => return Err(format!("expected an operator after {}", name))
+        };
+        let literal = self.parse_literal()?;
+        if literal.is_null() {
+            return Err(format!("use '{} IS NULL' to test for null", name));
+        }
+        Ok(Condition::Compare(name, operator, literal))
+    }
+
+    fn parse_text(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Text(text)) => Ok(text),
+            _ => Err("expected a quoted text".to_string())
+        }
+    }
+
+    fn parse_literal(&mut self) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Text(text)) => Ok(Value::String(text)),
+            Some(Token::Number(number)) => number.parse::<f64>().ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| format!("invalid number {}", number)),
+            Some(Token::Identifier(word)) if word.eq_ignore_ascii_case("true") => Ok(Value::Bool(true)),
+            Some(Token::Identifier(word)) if word.eq_ignore_ascii_case("false") => Ok(Value::Bool(false)),
+            Some(Token::Identifier(word)) if word.eq_ignore_ascii_case("null") => Ok(Value::Null),
+            Some(token) => Err(format!("expected a value, found {:?}", token)),
+            None => Err("expected a value".to_string())
+        }
+    }
+
+    fn build_regex(&self, pattern: &str) -> Result<Regex, String> {
+        let pattern = if self.ignore_case { format!("(?i){}", pattern) } else { pattern.to_string() };
+        Regex::new(&pattern).map_err(|e| e.to_string())
+    }
+}
+
+fn negate(condition: Condition, negated: bool) -> Condition {
+    if negated { Condition::Not(Box::new(condition)) } else { condition }
+}
+
+/// Converts a LIKE pattern to an anchored regular expression: % matches any text and _ a single character.
+fn like_to_regex(pattern: &str) -> String {
+    let body = pattern.chars()
+        .map(|c| match c {
+            '%' => ".*".to_string(),
+            '_' => ".".to_string(),
+            other => fancy_regex::escape(&other.to_string()).to_string()
+        })
+        .collect::<String>();
+    format!("(?s)^{}$", body)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn matches(expression: &str, record: &Value) -> bool {
+        parse_query(expression, false).unwrap().matches(record)
+    }
+
+    #[test]
+    fn tokenizes_fields_texts_numbers_and_symbols() {
+        assert_eq!(tokenize("age>=-1.5e3 AND \"first name\" <> 'O''Brien'"), Ok(vec![
+            Token::Identifier("age".to_string()),
+            Token::Symbol(">=".to_string()),
+            Token::Number("-1.5e3".to_string()),
+            Token::Identifier("AND".to_string()),
+            Token::Identifier("first name".to_string()),
+            Token::Symbol("<>".to_string()),
+            Token::Text("O'Brien".to_string()),
+        ]));
+        assert_eq!(tokenize("user.address.city ~ `x`"), Ok(vec![
+            Token::Identifier("user.address.city".to_string()),
+            Token::Symbol("~".to_string()),
+            Token::Identifier("x".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn rejects_invalid_tokens() {
+        assert_eq!(tokenize("name = 'x"), Err("unterminated ' quote".to_string()));
+        assert_eq!(tokenize("a ; b"), Err("unexpected character ';'".to_string()));
+    }
+
+    #[test]
+    fn and_binds_stronger_than_or() {
+        let record = json!({"a": 1, "b": 2, "c": 3});
+        assert!(matches("a = 0 AND b = 0 OR c = 3", &record));
+        assert!(!matches("a = 0 AND (b = 0 OR c = 3)", &record));
+        assert!(matches("NOT NOT a = 1", &record));
+    }
+
+    #[test]
+    fn converts_fields_to_the_type_of_the_literal() {
+        let record = json!({"age": "42", "active": "TRUE", "name": "Smith"});
+        assert!(matches("age > 30", &record));
+        assert!(matches("active = true", &record));
+        assert!(!matches("name > 30", &record));
+        assert!(matches("NOT name > 30", &record));
+    }
+
+    #[test]
+    fn matches_like_regexp_in_and_null() {
+        let record = json!({"email": "jane@example.com", "country": "UK", "tags": ["a", "b"], "phone": null});
+        assert!(matches("email LIKE '%@example._om'", &record));
+        assert!(matches("email NOT LIKE 'example%'", &record));
+        assert!(matches("email REGEXP '^j.*m$' AND email ~ 'example'", &record));
+        assert!(matches("country IN ('DE', 'UK')", &record));
+        assert!(matches("tags.1 = 'b'", &record));
+        assert!(matches("phone IS NULL AND missing IS NULL AND country IS NOT NULL", &record));
+    }
+
+    #[test]
+    fn ignores_case_of_texts_and_patterns() {
+        let query = parse_query("country = 'uk' AND email LIKE 'JANE%'", true).unwrap();
+        assert!(query.matches(&json!({"country": "UK", "email": "jane@example.com"})));
+    }
+
+    #[test]
+    fn rejects_invalid_queries() {
+        let error = |expression: &str| parse_query(expression, false).err().unwrap();
+        assert_eq!(error("age >"), "expected a value");
+        assert_eq!(error("(age > 1"), "expected )");
+        assert_eq!(error("age NOT = 1"), "expected LIKE, REGEXP or IN after NOT");
+        assert_eq!(error("age = null"), "use 'age IS NULL' to test for null");
+        assert_eq!(error("age = 1 2"), "unexpected Number(\"2\")");
+    }
+}
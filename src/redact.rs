@@ -0,0 +1,397 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use fancy_regex::Regex;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::cli::{Cli, Mode, RedactStrategy};
+use crate::finders::{build_regex, find_regex};
+use crate::io_ops::{glob_base, resolve_path, target_path};
+use crate::pii_search::{build_detectors, find_pii, type_name, Detector};
+use crate::secrets_search::{find_secrets, select_secret_rules, SecretRule};
+use crate::OutputPrinter;
+
+/// Extensions of archives which are rewritten with their text entries redacted.
+const ARCHIVE_EXTENSIONS: [&str; 4] = [".zip", ".jar", ".war", ".ear"];
+
+/// Number of bytes checked for zero bytes to tell binary from text content.
+const BINARY_CHECK_BYTES: usize = 8000;
+
+/// Number of hex digits of the HMAC-SHA256 which replace a value.
+const HASH_HEX_DIGITS: usize = 16;
+
+/// Finds the values to redact in a line the way the mode finds its matches, each with a label naming its kind.
+pub(crate) enum RedactMatcher {
+    Expression(Regex),
+    Secrets(Vec<SecretRule>),
+    Pii(Vec<Detector>, Option<Regex>),
+}
+
+impl RedactMatcher {
+    /// Returns the label and the position in the line of every value.
+    fn find(&self, line: &str) -> Vec<(String, Range<usize>)> {
+        let found = match self {
+            RedactMatcher::Expression(re) => re.find_iter(line).flatten()
+                .map(|m| ("value".to_string(), m.range()))
+                .collect::<Vec<(String, Range<usize>)>>(),
+            RedactMatcher::Secrets(rules) => find_secrets(line, rules).into_iter()
+                .map(|(id, span)| (id.to_string(), span))
+                .collect(),
+            RedactMatcher::Pii(detectors, search_filter) => find_pii(line, detectors).into_iter()
+                .filter(|(_, span)| search_filter.as_ref().map(|re| find_regex(&line[span.clone()], re)).unwrap_or(true))
+                .map(|(pii_type, span)| (type_name(pii_type).to_string(), span))
+                .collect(),
+        };
+        found.into_iter().filter(|(_, span)| !span.is_empty()).collect()
+    }
+}
+
+/// Checks the options of --redact and builds the matcher of the mode. Exits if the mode does not support
+/// redaction or --redacted-dir is missing.
+fn build_redact_matcher(cli: &Cli) -> RedactMatcher {
+    if cli.redacted_dir.is_none() {
+        eprintln!("Please enter the directory of the redacted copies with e.g: '--redacted-dir redacted'");
+        process::exit(0x0001);
+    }
+    resolve_redacted_dir(cli);
+    let search_expression = || match &cli.search_expression {
+        Some(search_expression) => search_expression,
+        None => {
+            eprintln!("Please enter the search expression with e.g: '--search-expression tb_'");
+            process::exit(0x0001);
+        }
+    };
+    match cli.mode {
+        Mode::LineSearch => RedactMatcher::Expression(build_regex(cli, search_expression(), true)),
+        Mode::LineRegexSearch => RedactMatcher::Expression(build_regex(cli, search_expression(), false)),
+        Mode::Secrets => RedactMatcher::Secrets(select_secret_rules(cli)),
+        Mode::Pii => RedactMatcher::Pii(build_detectors(&cli.pii_type),
+                                        cli.search_expression.as_ref().map(|e| build_regex(cli, e, true))),
+        _ => {
+            eprintln!("--redact is supported in line-search, line-regex-search, secrets and pii mode");
+            process::exit(0x0001);
+        }
+    }
+}
+
+/// The key of --redact-key or a random key. Exits if --redact-key is given without --redact hash.
+fn redact_key(cli: &Cli) -> Vec<u8> {
+    match (&cli.redact, &cli.redact_key) {
+        (Some(RedactStrategy::Hash), Some(key)) => key.as_bytes().to_vec(),
+        (_, Some(_)) => {
+            eprintln!("--redact-key is only used with --redact hash");
+            process::exit(0x0001);
+        }
+        (_, None) => {
+            let mut key = vec![0; 32];
+            getrandom::getrandom(&mut key).expect("Could not get a random key");
+            key
+        }
+    }
+}
+
+/// The absolute path of --redacted-dir. Exits if it contains the searched files, as the copies would replace them.
+pub(crate) fn resolve_redacted_dir(cli: &Cli) -> PathBuf {
+    let redacted_dir = cli.redacted_dir.as_ref().unwrap();
+    let resolved = resolve_path(Path::new(redacted_dir)).and_then(|dir| {
+        resolve_path(&glob_base(&cli.glob_pattern)).map(|base| (dir, base))
+    });
+    match resolved {
+        Ok((dir, base)) if base.starts_with(&dir) => {
+            eprintln!("The directory of the redacted copies {} contains the searched files, please use another one",
+                      redacted_dir);
+            process::exit(0x0001);
+        }
+        Ok((dir, _)) => dir,
+        Err(e) => {
+            eprintln!("Invalid directory of the redacted copies {}: {}", redacted_dir, e);
+            process::exit(0x0001);
+        }
+    }
+}
+
+/// Replaces values consistently: every occurrence of a value gets the same replacement.
+struct Redactor<'a> {
+    strategy: &'a RedactStrategy,
+    /// Key of the hash, so values cannot be found by hashing candidates without it.
+    key: Vec<u8>,
+    replacements: HashMap<String, String>,
+    counters: HashMap<String, usize>,
+    /// Label, value and replacement in the order the values were found.
+    mapping: Vec<(String, String, String)>,
+}
+
+impl Redactor<'_> {
+    fn replacement(&mut self, label: &str, value: &str) -> String {
+        if let Some(replacement) = self.replacements.get(value) {
+            return replacement.clone();
+        }
+        let replacement = match self.strategy {
+            RedactStrategy::Mask => "*".repeat(value.chars().count()),
+            RedactStrategy::Hash => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+                mac.update(value.as_bytes());
+                let hash = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect::<String>();
+                hash[..HASH_HEX_DIGITS].to_string()
+            }
+            RedactStrategy::Pseudonym => {
+                let counter = self.counters.entry(label.to_string()).or_default();
+                *counter += 1;
+                format!("{}_{}", label.to_uppercase().replace('-', "_"), counter)
+            }
+        };
+        self.replacements.insert(value.to_string(), replacement.clone());
+        self.mapping.push((label.to_string(), value.to_string(), replacement.clone()));
+        replacement
+    }
+
+    /// Redacts the text line by line, keeping the line endings, and returns it with the number of values replaced.
+    fn redact_text(&mut self, text: &str, matcher: &RedactMatcher) -> (String, usize) {
+        let mut redacted = String::with_capacity(text.len());
+        let mut count = 0;
+        for line_with_ending in text.split_inclusive('\n') {
+            let line = line_with_ending.trim_end_matches(['\r', '\n']);
+            let line_ending = &line_with_ending[line.len()..];
+            let mut found = matcher.find(line);
+            // Where values overlap, the one starting first and then the longest one is replaced
+            found.sort_by_key(|(_, span)| (span.start, Reverse(span.end)));
+            let mut position = 0;
+            for (label, span) in found {
+                if span.start < position {
+                    continue;
+                }
+                redacted.push_str(&line[position..span.start]);
+                redacted.push_str(&self.replacement(&label, &line[span.clone()]));
+                position = span.end;
+                count += 1;
+            }
+            redacted.push_str(&line[position..]);
+            redacted.push_str(line_ending);
+        }
+        (redacted, count)
+    }
+}
+
+/// Writes a redacted copy of every file with hits while searching, before --exec and the file actions get to the
+/// file, and prints "file :: redacted copy :: copy" for each. The mapping of the values to their replacements is only
+/// written if --redact-mapping is given.
+pub(crate) struct Redaction<'a> {
+    matcher: RedactMatcher,
+    redactor: Redactor<'a>,
+    redacted_dir: PathBuf,
+    resolved_redacted_dir: PathBuf,
+    base: PathBuf,
+    files: usize,
+    values: usize,
+}
+
+impl Redaction<'_> {
+    /// Returns None if --redact is not given. Exits if the options of --redact are not valid.
+    pub(crate) fn from_cli(cli: &Cli) -> Option<Redaction<'_>> {
+        let strategy = cli.redact.as_ref()?;
+        Some(Redaction {
+            matcher: build_redact_matcher(cli),
+            redactor: Redactor {
+                strategy,
+                key: redact_key(cli),
+                replacements: HashMap::new(),
+                counters: HashMap::new(),
+                mapping: vec![],
+            },
+            redacted_dir: PathBuf::from(cli.redacted_dir.as_ref().unwrap()),
+            resolved_redacted_dir: resolve_redacted_dir(cli),
+            base: resolve_path(&glob_base(&cli.glob_pattern)).unwrap_or_default(),
+            files: 0,
+            values: 0,
+        })
+    }
+
+    pub(crate) fn file_processed(&mut self, path: &Path, output: &mut dyn OutputPrinter) {
+        let file_name = path.to_str().unwrap();
+        let resolved_path = match resolve_path(path) {
+            Ok(resolved_path) => resolved_path,
+            Err(e) => {
+                output.err_output(format!("Could not process path {:?} due to {}", path, e).as_str());
+                return;
+            }
+        };
+        // Copies written by an earlier run are not redacted again
+        if path.is_dir() || resolved_path.starts_with(&self.resolved_redacted_dir) {
+            return;
+        }
+        let target = target_path(&self.redacted_dir, &self.base, &resolved_path);
+        let result = if resolve_path(&target).map(|t| t == resolved_path).unwrap_or(true) {
+            Err("the copy would replace the file".to_string())
+        } else if is_archive(file_name) {
+            redact_archive(path, &target, &self.matcher, &mut self.redactor)
+        } else {
+            redact_file(path, &target, &self.matcher, &mut self.redactor)
+        };
+        match result {
+            Ok(0) => {}
+            Ok(count) => {
+                self.files += 1;
+                self.values += count;
+                output.output(format!("{} :: redacted copy :: {}", file_name, target.display()).as_str());
+            }
+            Err(e) => output.err_output(format!("{} :: Could not write redacted copy {}: {}",
+                                                file_name, target.display(), e).as_str())
+        }
+    }
+
+    /// Writes the mapping and prints the total.
+    pub(crate) fn finish(&self, cli: &Cli, output: &mut dyn OutputPrinter) {
+        if let Some(mapping_file) = &cli.redact_mapping {
+            if let Err(e) = write_mapping(mapping_file, &self.redactor.mapping) {
+                output.err_output(format!("Could not write mapping file {}: {}", mapping_file, e).as_str());
+            }
+        }
+        output.output(format!("Redacted: {} values in {} files", self.values, self.files).as_str());
+    }
+}
+
+fn is_archive(name: &str) -> bool {
+    let lower_name = name.to_lowercase();
+    ARCHIVE_EXTENSIONS.iter().any(|e| lower_name.ends_with(e))
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_CHECK_BYTES)].contains(&0)
+}
+
+/// Content which is not valid UTF-8 cannot be rewritten without changing it, so it is refused if it has
+/// values to redact.
+fn check_undecodable(name: &str, bytes: &[u8], matcher: &RedactMatcher) -> Result<usize, String> {
+    if String::from_utf8_lossy(bytes).lines().any(|line| !matcher.find(line).is_empty()) {
+        Err(format!("{} is not valid UTF-8", name))
+    } else {
+        Ok(0)
+    }
+}
+
+fn redact_file(path: &Path, target: &Path, matcher: &RedactMatcher, redactor: &mut Redactor) -> Result<usize, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    if is_binary(&bytes) {
+        return Ok(0);
+    }
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(text) => text,
+        Err(_) => return check_undecodable("the file", &bytes, matcher)
+    };
+    let (redacted, count) = redactor.redact_text(text, matcher);
+    if count > 0 {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(target, redacted).map_err(|e| e.to_string())?;
+    }
+    Ok(count)
+}
+
+/// Rewrites the archive with its redacted text entries. All other entries, including nested archives,
+/// are copied without decompressing them.
+fn redact_archive(path: &Path, target: &Path, matcher: &RedactMatcher, redactor: &mut Redactor) -> Result<usize, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let mut redacted_entries: HashMap<usize, Vec<u8>> = HashMap::new();
+    let mut count = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        if entry.is_dir() || is_archive(entry.name()) {
+            continue;
+        }
+        let mut bytes = vec![];
+        entry.read_to_end(&mut bytes).map_err(|e| format!("{}: {}", entry.name(), e))?;
+        if is_binary(&bytes) {
+            continue;
+        }
+        let text = match std::str::from_utf8(&bytes) {
+            Ok(text) => text,
+            Err(_) => {
+                check_undecodable(entry.name(), &bytes, matcher)?;
+                continue;
+            }
+        };
+        let (redacted, found) = redactor.redact_text(text, matcher);
+        if found > 0 {
+            count += found;
+            redacted_entries.insert(i, redacted.into_bytes());
+        }
+    }
+    if count == 0 {
+        return Ok(0);
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut writer = ZipWriter::new(File::create(target).map_err(|e| e.to_string())?);
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        match redacted_entries.remove(&i) {
+            Some(bytes) => {
+                let mut options = FileOptions::default()
+                    .compression_method(entry.compression())
+                    .last_modified_time(entry.last_modified());
+                if let Some(mode) = entry.unix_mode() {
+                    options = options.unix_permissions(mode);
+                }
+                let name = entry.name().to_string();
+                drop(entry);
+                writer.start_file(name, options).map_err(|e| e.to_string())?;
+                writer.write_all(&bytes).map_err(|e| e.to_string())?;
+            }
+            None => writer.raw_copy_file(entry).map_err(|e| e.to_string())?
+        }
+    }
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(count)
+}
+
+fn write_mapping(mapping_file: &str, mapping: &[(String, String, String)]) -> Result<(), String> {
+    let mut writer = csv::Writer::from_path(mapping_file).map_err(|e| e.to_string())?;
+    writer.write_record(["kind", "value", "replacement"]).map_err(|e| e.to_string())?;
+    for (label, value, replacement) in mapping {
+        writer.write_record([label, value, replacement]).map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(text: &str, pattern: &str, strategy: &RedactStrategy) -> (String, usize) {
+        let mut redactor = Redactor {
+            strategy,
+            key: b"key".to_vec(),
+            replacements: HashMap::new(),
+            counters: HashMap::new(),
+            mapping: vec![],
+        };
+        redactor.redact_text(text, &RedactMatcher::Expression(Regex::new(pattern).unwrap()))
+    }
+
+    #[test]
+    fn only_replaces_the_matches() {
+        assert_eq!(redact("concatenate cat\r\ncat", r"\bcat\b", &RedactStrategy::Mask),
+                   ("concatenate ***\r\n***".to_string(), 2));
+    }
+
+    #[test]
+    fn does_not_replace_inside_replacements() {
+        assert_eq!(redact("1 12 1", r"\d+", &RedactStrategy::Pseudonym), ("VALUE_1 VALUE_2 VALUE_1".to_string(), 3));
+    }
+
+    #[test]
+    fn replaces_overlapping_matches_once() {
+        assert_eq!(redact("abcd", "abc|bcd|abcd", &RedactStrategy::Mask), ("***d".to_string(), 1));
+    }
+}
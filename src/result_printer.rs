@@ -11,9 +11,35 @@ pub(crate) struct Statistics {
     pub errors: u32
 }
 
+/// The parts of a hit which --exec passes to commands. Every mode sets the parts it knows.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HitFields {
+    /// The archive entry, or the file inside the searched file, containing the hit.
+    pub entry: Option<String>,
+    /// The line of the hit, counted from 1 like editors do.
+    pub line: Option<u64>,
+    /// The byte offset of the hit in binary files.
+    pub offset: Option<u64>,
+    /// The matching text, e.g. the line or the value.
+    pub matched: Option<String>,
+}
+
+impl HitFields {
+    /// True if the mode knows none of the parts of the hit.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entry.is_none() && self.line.is_none() && self.offset.is_none() && self.matched.is_none()
+    }
+}
+
 pub(crate) trait OutputPrinter {
     fn print_param_map(&mut self, map: HashMap<String, String>);
     fn output_with_stats(&mut self, msg: &str);
+
+    /// Prints a hit like output_with_stats, together with its parts for printers which use them.
+    fn output_hit(&mut self, msg: &str, _fields: HitFields) {
+        self.output_with_stats(msg);
+    }
+
     fn output(&mut self, msg: &str);
     fn err_output(&mut self, msg: &str);
     fn get_name(&self) -> &str;
@@ -74,9 +100,8 @@ pub(crate) struct FilePrinter<'a> {
 
 pub(crate) fn print_msg(file: &File, path: &Path, msg: &str, what: &str) {
 
-    match file.borrow().write_all(format!("{}\r\n", msg).as_bytes()) {
-        Err(why) => panic!("Could not write {} to {}: {}", what, path.display(), why),
-        Ok(_) => {}
+    if let Err(why) = file.borrow().write_all(format!("{}\r\n", msg).as_bytes()) {
+        panic!("Could not write {} to {}: {}", what, path.display(), why)
     }
 }
 
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+
+use crate::cli::Cli;
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+pub(crate) fn process_sqlite_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    let search_expression = cli.search_expression.as_ref().unwrap();
+    if needs_regex(cli) {
+        let re = build_regex(cli, search_expression, true);
+        process_sqlite_search_generic(path, cli, &re, find_regex, output);
+    } else {
+        process_sqlite_search_generic(path, cli, search_expression, find_simple, output);
+    }
+}
+
+/// Prints "db :: table :: rowid :: column :: value" for every matching text value in the tables of the database.
+/// The database is opened read-only and all tables are searched unless tables are given on the command line.
+fn process_sqlite_search_generic<T>(path: PathBuf, cli: &Cli, search_filter: &T,
+                                    find_fn: fn(&str, &T) -> bool,
+                                    output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().unwrap();
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let connection = match Connection::open_with_flags(&path, flags) {
+        Ok(connection) => connection,
+        Err(e) => {
+            output.err_output(format!("Could not open database '{}': {}", file_name, e).as_str());
+            return;
+        }
+    };
+    let tables = match list_tables(&connection) {
+        Ok(tables) => tables,
+        Err(e) => {
+            output.err_output(format!("{} :: {}", file_name, e).as_str());
+            return;
+        }
+    };
+    let selected = if cli.table.is_empty() {
+        tables
+    } else {
+        for table in cli.table.iter().filter(|t| !tables.contains(t)) {
+            output.err_output(format!("{} :: Table '{}' not found", file_name, table).as_str());
+        }
+        tables.into_iter().filter(|t| cli.table.contains(t)).collect()
+    };
+    for table in selected {
+        let location = format!("{} :: {}", file_name, table);
+        if let Err(e) = search_table(&connection, &location, &table, search_filter, find_fn, output) {
+            output.err_output(format!("{} :: {}", location, e).as_str());
+        }
+    }
+}
+
+fn list_tables(connection: &Connection) -> rusqlite::Result<Vec<String>> {
+    let mut statement = connection.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")?;
+    let tables = statement.query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(tables)
+}
+
+/// Searches the text values of all columns, as sqlite columns may hold text whatever their declared type.
+/// Tables created without rowid report "?" instead of the rowid.
+fn search_table<T>(connection: &Connection, location: &str, table: &str, search_filter: &T,
+                   find_fn: fn(&str, &T) -> bool,
+                   output: &mut dyn OutputPrinter) -> rusqlite::Result<()> {
+    let quoted = format!("\"{}\"", table.replace('"', "\"\""));
+    let mut statement = match connection.prepare(&format!("SELECT rowid, * FROM {}", quoted)) {
+        Ok(statement) => statement,
+        Err(_) => connection.prepare(&format!("SELECT NULL, * FROM {}", quoted))?
+    };
+    let columns = statement.column_names().iter().map(|c| c.to_string()).collect::<Vec<String>>();
+    let mut rows = statement.query([])?;
+    while let Some(row) = rows.next()? {
+        let rowid = match row.get_ref(0)? {
+            ValueRef::Integer(id) => id.to_string(),
+            _ => "?".to_string()
+        };
+        for (index, column) in columns.iter().enumerate().skip(1) {
+            if let ValueRef::Text(bytes) = row.get_ref(index)? {
+                let value = String::from_utf8_lossy(bytes);
+                if find_fn(&value, search_filter) {
+                    let fields = HitFields { matched: Some(value.trim().to_string()), ..HitFields::default() };
+                    output.output_hit(format!("{} :: {} :: {} :: {}", location, rowid, column,
+                                              value.trim()).as_str(), fields);
+                }
+            }
+        }
+    }
+    Ok(())
+}
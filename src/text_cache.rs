@@ -0,0 +1,213 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::cli::Cli;
+use crate::OutputPrinter;
+
+/// Extracted text which can be stored in the cache, like the text of a pdf page or a located text of an office file.
+pub(crate) trait Cacheable: Sized {
+    fn to_json(&self) -> Value;
+
+    fn from_json(value: &Value) -> Option<Self>;
+}
+
+impl Cacheable for String {
+    fn to_json(&self) -> Value {
+        json!(self)
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        value.as_str().map(|s| s.to_string())
+    }
+}
+
+impl Cacheable for (String, String) {
+    fn to_json(&self) -> Value {
+        json!([self.0, self.1])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some((value.get(0)?.as_str()?.to_string(), value.get(1)?.as_str()?.to_string()))
+    }
+}
+
+/// On-disk cache of text extracted from expensive formats, like pdf and office files.
+/// Every entry is a json file named after the hash of the source path, or of the content for sources without a path
+/// like email attachments. It stores the size, modification time and content hash of the source next to the
+/// extracted pages or texts. The source is only hashed if its size or modification time changed or there is no entry.
+/// Entries which cannot be written are reported as errors, the search goes on with the extracted text.
+pub(crate) struct TextCache {
+    dir: PathBuf,
+}
+
+impl TextCache {
+    pub(crate) fn from_cli(cli: &Cli) -> Option<TextCache> {
+        cli.cache_dir.as_ref().map(|dir| TextCache { dir: PathBuf::from(dir) })
+    }
+
+    /// Returns the cached pages of the file or calls the extractor and stores its result.
+    /// A file whose content is unchanged after e.g. a copy gets its entry updated with the new modification time.
+    pub(crate) fn get_or_extract<T: Cacheable>(&self, path: &Path,
+                                               extract_fn: fn(&Path) -> Result<Vec<T>, String>,
+                                               output: &mut dyn OutputPrinter) -> Result<Vec<T>, String> {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(_) => return extract_fn(path)
+        };
+        let entry_path = self.entry_path(path);
+        let entry = read_entry(&entry_path);
+        let (size, modified) = (metadata.len(), modified_nanos(&metadata));
+        if let Some(pages) = entry.as_ref().filter(|e| same_file(e, size, modified)).and_then(pages_of) {
+            return Ok(pages);
+        }
+        let hash = match fs::read(path) {
+            Ok(content) => hex_digest(&content),
+            Err(_) => return extract_fn(path)
+        };
+        let key = CacheKey { path: path.to_string_lossy().to_string(), size, modified, hash };
+        let pages = match entry.as_ref().filter(|e| key.same_content(e)).and_then(pages_of) {
+            Some(pages) => pages,
+            None => extract_fn(path)?
+        };
+        self.write_entry(&entry_path, &key, &pages, output);
+        Ok(pages)
+    }
+
+    /// Like get_or_extract for content without a file of its own, like email attachments.
+    pub(crate) fn get_or_extract_bytes<T: Cacheable>(&self, bytes: &[u8],
+                                                     extract_fn: fn(&[u8]) -> Result<Vec<T>, String>,
+                                                     output: &mut dyn OutputPrinter) -> Result<Vec<T>, String> {
+        let key = CacheKey::from_bytes(bytes);
+        let entry_path = self.dir.join(format!("{}.json", key.hash));
+        if let Some(pages) = read_entry(&entry_path).filter(|e| key.same_content(e)).and_then(|e| pages_of(&e)) {
+            return Ok(pages);
+        }
+        let pages = extract_fn(bytes)?;
+        self.write_entry(&entry_path, &key, &pages, output);
+        Ok(pages)
+    }
+
+    pub(crate) fn clear(&self) -> std::io::Result<()> {
+        for entry in self.entries()? {
+            fs::remove_file(entry.0)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the least recently written entries until the cache fits into `max_bytes`.
+    pub(crate) fn shrink_to(&self, max_bytes: u64) -> std::io::Result<()> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|(_, _, modified)| *modified);
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        for (path, size, _) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= size;
+        }
+        Ok(())
+    }
+
+    fn entries(&self) -> std::io::Result<Vec<(PathBuf, u64, u128)>> {
+        let mut entries = vec![];
+        if !self.dir.exists() {
+            return Ok(entries);
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().map(|e| e == "json").unwrap_or(false) {
+                let metadata = fs::metadata(&path)?;
+                entries.push((path, metadata.len(), modified_nanos(&metadata)));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// The cache is an optimisation only, so a failure to write it is reported without failing the search.
+    fn write_entry<T: Cacheable>(&self, entry_path: &Path, key: &CacheKey, pages: &[T],
+                                 output: &mut dyn OutputPrinter) {
+        let entry = json!({
+            "path": key.path,
+            "size": key.size,
+            "modified": key.modified.to_string(),
+            "hash": key.hash,
+            "pages": pages.iter().map(|page| page.to_json()).collect::<Vec<Value>>(),
+        });
+        if let Err(e) = fs::create_dir_all(&self.dir).and_then(|_| fs::write(entry_path, entry.to_string())) {
+            output.err_output(format!("Could not write cache entry {:?} due to {}", entry_path, e).as_str());
+        }
+    }
+
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        let absolute = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let name = hex_digest(absolute.to_string_lossy().as_bytes());
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+struct CacheKey {
+    path: String,
+    size: u64,
+    modified: u128,
+    hash: String,
+}
+
+impl CacheKey {
+    fn from_bytes(bytes: &[u8]) -> CacheKey {
+        CacheKey {
+            path: String::new(),
+            size: bytes.len() as u64,
+            modified: 0,
+            hash: hex_digest(bytes),
+        }
+    }
+
+    fn same_content(&self, entry: &Value) -> bool {
+        entry["size"].as_u64() == Some(self.size) && entry["hash"].as_str() == Some(self.hash.as_str())
+    }
+}
+
+/// True if the entry was written for a file of this size and modification time, which is taken as unchanged.
+/// Files without a modification time are always hashed.
+fn same_file(entry: &Value, size: u64, modified: u128) -> bool {
+    modified != 0
+        && entry["size"].as_u64() == Some(size)
+        && entry["modified"].as_str() == Some(modified.to_string().as_str())
+}
+
+fn read_entry(entry_path: &Path) -> Option<Value> {
+    let content = fs::read_to_string(entry_path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn pages_of<T: Cacheable>(entry: &Value) -> Option<Vec<T>> {
+    entry["pages"].as_array()?.iter()
+        .map(T::from_json)
+        .collect()
+}
+
+fn modified_nanos(metadata: &fs::Metadata) -> u128 {
+    metadata.modified().ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+}
+
+fn hex_digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Extracts the pages or texts of the file, going through the cache when one is configured.
+pub(crate) fn extract_cached<T: Cacheable>(cli: &Cli, path: &Path,
+                                           extract_fn: fn(&Path) -> Result<Vec<T>, String>,
+                                           output: &mut dyn OutputPrinter) -> Result<Vec<T>, String> {
+    match TextCache::from_cli(cli) {
+        Some(cache) => cache.get_or_extract(path, extract_fn, output),
+        None => extract_fn(path)
+    }
+}
@@ -0,0 +1,384 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use html5ever::tendril::StrTendril;
+use html5ever::tokenizer::states::RawKind;
+use html5ever::tokenizer::{BufferQueue, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+use serde_json::{json, Value};
+
+use crate::cli::{Cli, TextElement};
+use crate::finders::{build_regex, find_regex, find_simple, needs_regex};
+use crate::text_cache::{extract_cached, Cacheable};
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+/// Elements whose content is never shown.
+const HIDDEN_ELEMENTS: [&str; 4] = ["script", "style", "template", "noscript"];
+
+/// Elements which do not end the current block of text.
+const INLINE_ELEMENTS: [&str; 20] = ["a", "abbr", "b", "bdi", "bdo", "cite", "code", "data", "dfn", "em", "i", "kbd",
+    "mark", "q", "s", "small", "span", "strong", "sub", "sup"];
+
+/// Elements without content and end tag.
+const VOID_ELEMENTS: [&str; 14] = ["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
+    "source", "track", "wbr", "param"];
+
+const HEADINGS: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+
+pub(crate) fn process_text_search(path: PathBuf, cli: &Cli, output: &mut dyn OutputPrinter) {
+    let search_expression = cli.search_expression.as_ref().unwrap();
+    if needs_regex(cli) {
+        let re = build_regex(cli, search_expression, true);
+        process_text_search_generic(path, cli, &re, find_regex, output);
+    } else {
+        process_text_search_generic(path, cli, search_expression, find_simple, output);
+    }
+}
+
+/// Prints "file :: line :: element :: text" for every matching piece of text of HTML and Markdown files.
+/// The line is the line of the source the text starts on, the element is e.g. "p", "h2", "a@href" or "img@alt".
+fn process_text_search_generic<T>(path: PathBuf, cli: &Cli, search_filter: &T,
+                                  find_fn: fn(&str, &T) -> bool,
+                                  output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().unwrap();
+    let segments = match extract_cached(cli, &path, extract_text_segments, output) {
+        Ok(segments) => segments,
+        Err(e) => {
+            output.err_output(format!("Could not extract text from '{}': {}", file_name, e).as_str());
+            return;
+        }
+    };
+    for segment in segments.iter().filter(|s| s.selected(&cli.text_element)) {
+        if find_fn(&segment.text, search_filter) {
+            let fields = HitFields {
+                line: Some(segment.line),
+                matched: Some(segment.text.clone()),
+                ..HitFields::default()
+            };
+            output.output_hit(format!("{} :: {} :: {} :: {}", file_name, segment.line, segment.element,
+                                      segment.text).as_str(), fields);
+        }
+    }
+}
+
+/// Extracts the text of HTML and Markdown files based on the file extension.
+fn extract_text_segments(path: &Path) -> Result<Vec<TextSegment>, String> {
+    let extension = path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    match extension.as_str() {
+        "html" | "htm" | "xhtml" => Ok(extract_html(&content, 0)),
+        "md" | "markdown" => Ok(extract_markdown(&content)),
+        _ => Err(format!("Unsupported text format '{}'", extension))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SegmentKind {
+    Text,
+    Link,
+    Alt,
+}
+
+/// A piece of text without markup and with collapsed whitespace.
+struct TextSegment {
+    line: u64,
+    element: String,
+    kind: SegmentKind,
+    text: String,
+}
+
+impl Cacheable for TextSegment {
+    fn to_json(&self) -> Value {
+        let kind = match self.kind {
+            SegmentKind::Text => "text",
+            SegmentKind::Link => "link",
+            SegmentKind::Alt => "alt",
+        };
+        json!([self.line, self.element, kind, self.text])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let kind = match value.get(2)?.as_str()? {
+            "text" => SegmentKind::Text,
+            "link" => SegmentKind::Link,
+            "alt" => SegmentKind::Alt,
+            _ => return None
+        };
+        Some(TextSegment {
+            line: value.get(0)?.as_u64()?,
+            element: value.get(1)?.as_str()?.to_string(),
+            kind,
+            text: value.get(3)?.as_str()?.to_string(),
+        })
+    }
+}
+
+impl TextSegment {
+    /// Only visible text is selected if no elements are given on the command line.
+    fn selected(&self, elements: &[TextElement]) -> bool {
+        let accepts = |element: &TextElement| match element {
+            TextElement::Visible => self.kind == SegmentKind::Text,
+            TextElement::Headings => self.kind == SegmentKind::Text && HEADINGS.contains(&self.element.as_str()),
+            TextElement::Links => self.kind == SegmentKind::Link,
+            TextElement::Alt => self.kind == SegmentKind::Alt,
+        };
+        if elements.is_empty() { accepts(&TextElement::Visible) } else { elements.iter().any(accepts) }
+    }
+}
+
+/// Collects text into blocks, which end at the start and end of block elements, and into spans like
+/// link texts which are reported separately. Alt texts of images are not visible, so they only go into their span.
+#[derive(Default)]
+struct SegmentCollector {
+    segments: Vec<TextSegment>,
+    block: String,
+    block_line: Option<u64>,
+    spans: Vec<(SegmentKind, String, u64)>,
+}
+
+impl SegmentCollector {
+    fn push_text(&mut self, text: &str, line: u64) {
+        for span in self.spans.iter_mut() {
+            span.1.push_str(text);
+        }
+        if self.spans.iter().any(|(kind, _, _)| *kind == SegmentKind::Alt) {
+            return;
+        }
+        if self.block_line.is_none() && !text.trim().is_empty() {
+            self.block_line = Some(line);
+        }
+        self.block.push_str(text);
+    }
+
+    fn end_block(&mut self, element: &str) {
+        let text = std::mem::take(&mut self.block);
+        if let Some(line) = self.block_line.take() {
+            self.add(line, element, SegmentKind::Text, &text);
+        }
+    }
+
+    fn open_span(&mut self, kind: SegmentKind, line: u64) {
+        self.spans.push((kind, String::new(), line));
+    }
+
+    fn close_span(&mut self, element: &str) {
+        if let Some((kind, text, line)) = self.spans.pop() {
+            self.add(line, element, kind, &text);
+        }
+    }
+
+    fn add(&mut self, line: u64, element: &str, kind: SegmentKind, text: &str) {
+        let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if !text.is_empty() {
+            self.segments.push(TextSegment { line, element: element.to_string(), kind, text });
+        }
+    }
+}
+
+/// Receives the tokens of the html tokenizer, which are tagged with the line they were found on.
+struct HtmlSink {
+    collector: SegmentCollector,
+    open_elements: Vec<String>,
+    hidden_depth: usize,
+    line_offset: u64,
+}
+
+impl HtmlSink {
+    /// The innermost open block element, which the current text belongs to.
+    fn current_block(&self) -> String {
+        self.open_elements.iter().rev()
+            .find(|e| !INLINE_ELEMENTS.contains(&e.as_str()))
+            .cloned()
+            .unwrap_or_else(|| "text".to_string())
+    }
+}
+
+impl TokenSink for HtmlSink {
+    type Handle = ();
+
+    fn process_token(&mut self, token: Token, line_number: u64) -> TokenSinkResult<()> {
+        let line = line_number + self.line_offset;
+        match token {
+            Token::TagToken(tag) => {
+                let name = tag.name.to_string();
+                let is_inline = INLINE_ELEMENTS.contains(&name.as_str());
+                match tag.kind {
+                    TagKind::StartTag => {
+                        if HIDDEN_ELEMENTS.contains(&name.as_str()) {
+                            if !tag.self_closing {
+                                self.hidden_depth += 1;
+                                self.open_elements.push(name.clone());
+                            }
+                            return match name.as_str() {
+                                "script" => TokenSinkResult::RawData(RawKind::ScriptData),
+                                "style" | "noscript" => TokenSinkResult::RawData(RawKind::Rawtext),
+                                _ => TokenSinkResult::Continue
+                            };
+                        }
+                        if self.hidden_depth > 0 {
+                            return TokenSinkResult::Continue;
+                        }
+                        if !is_inline {
+                            self.collector.end_block(&self.current_block());
+                        }
+                        for attribute in &tag.attrs {
+                            let attribute_name = attribute.name.local.to_string();
+                            match attribute_name.as_str() {
+                                "alt" => self.collector.add(line, &format!("{}@alt", name), SegmentKind::Alt, &attribute.value),
+                                "href" if name == "a" => self.collector.add(line, "a@href", SegmentKind::Link, &attribute.value),
+                                _ => {}
+                            }
+                        }
+                        if name == "br" {
+                            self.collector.push_text("\n", line);
+                        }
+                        if !tag.self_closing && !VOID_ELEMENTS.contains(&name.as_str()) {
+                            if name == "a" {
+                                self.collector.open_span(SegmentKind::Link, line);
+                            }
+                            self.open_elements.push(name.clone());
+                        }
+                        match name.as_str() {
+                            "title" | "textarea" => TokenSinkResult::RawData(RawKind::Rcdata),
+                            _ => TokenSinkResult::Continue
+                        }
+                    }
+                    TagKind::EndTag => {
+                        let position = match self.open_elements.iter().rposition(|e| *e == name) {
+                            Some(position) => position,
+                            None => return TokenSinkResult::Continue
+                        };
+                        if self.hidden_depth > 0 {
+                            if HIDDEN_ELEMENTS.contains(&name.as_str()) {
+                                self.hidden_depth -= 1;
+                                self.open_elements.truncate(position);
+                            }
+                            return TokenSinkResult::Continue;
+                        }
+                        if !is_inline {
+                            self.collector.end_block(&self.current_block());
+                        }
+                        // Closes elements which were left open, like list items without end tag
+                        for closed in self.open_elements.split_off(position).iter().rev() {
+                            if closed == "a" {
+                                self.collector.close_span("a");
+                            }
+                        }
+                        TokenSinkResult::Continue
+                    }
+                }
+            }
+            Token::CharacterTokens(text) => {
+                if self.hidden_depth == 0 {
+                    // Text tokens are reported on the line they end on
+                    let trailing_lines = text.trim_start().matches('\n').count() as u64;
+                    self.collector.push_text(&text, line.saturating_sub(trailing_lines).max(1));
+                }
+                TokenSinkResult::Continue
+            }
+            _ => TokenSinkResult::Continue
+        }
+    }
+}
+
+/// Extracts the text of the html. The line offset is added to the lines, for html embedded into other formats.
+fn extract_html(content: &str, line_offset: u64) -> Vec<TextSegment> {
+    let sink = HtmlSink {
+        collector: SegmentCollector::default(),
+        open_elements: vec![],
+        hidden_depth: 0,
+        line_offset,
+    };
+    let mut tokenizer = Tokenizer::new(sink, TokenizerOpts::default());
+    let mut queue = BufferQueue::new();
+    queue.push_back(StrTendril::from(content));
+    let _ = tokenizer.feed(&mut queue);
+    tokenizer.end();
+    let mut sink = tokenizer.sink;
+    let block = sink.current_block();
+    sink.collector.end_block(&block);
+    sink.collector.segments
+}
+
+/// Extracts the text of the markdown. Images contribute their alt text, embedded html is stripped.
+fn extract_markdown(content: &str) -> Vec<TextSegment> {
+    let line_starts = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect::<Vec<usize>>();
+    let line_of = |offset: usize| line_starts.partition_point(|start| *start <= offset) as u64;
+    let mut collector = SegmentCollector::default();
+    let mut blocks: Vec<String> = vec![];
+    let current_block = |blocks: &Vec<String>| blocks.last().cloned().unwrap_or_else(|| "text".to_string());
+    // Html blocks arrive line by line, they are tokenized together so elements like script can span lines
+    let mut html: Option<(u64, String)> = None;
+    let flush_html = |html: &mut Option<(u64, String)>, collector: &mut SegmentCollector, blocks: &Vec<String>| {
+        if let Some((line, content)) = html.take() {
+            let segments = extract_html(&content, line - 1);
+            // Inline html is mostly just a tag, text in between continues the block
+            if !segments.is_empty() {
+                collector.end_block(&current_block(blocks));
+                collector.segments.extend(segments);
+            }
+        }
+    };
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_FOOTNOTES;
+    for (event, range) in Parser::new_ext(content, options).into_offset_iter() {
+        let line = line_of(range.start);
+        if let Event::Html(content) = &event {
+            let (start, buffer) = html.get_or_insert_with(|| (line, String::new()));
+            // Blank lines between html blocks have no events, they are added to keep the line numbers
+            let buffered_lines = *start + buffer.matches('\n').count() as u64;
+            buffer.push_str(&"\n".repeat(line.saturating_sub(buffered_lines) as usize));
+            buffer.push_str(content);
+            continue;
+        }
+        flush_html(&mut html, &mut collector, &blocks);
+        match event {
+            Event::Start(Tag::Link(_, url, _)) => {
+                collector.add(line, "a@href", SegmentKind::Link, &url);
+                collector.open_span(SegmentKind::Link, line);
+            }
+            Event::End(Tag::Link(..)) => collector.close_span("a"),
+            Event::Start(Tag::Image(..)) => collector.open_span(SegmentKind::Alt, line),
+            Event::End(Tag::Image(..)) => collector.close_span("img@alt"),
+            Event::Start(tag) => {
+                if let Some(element) = markdown_block(&tag) {
+                    collector.end_block(&current_block(&blocks));
+                    blocks.push(element);
+                }
+            }
+            Event::End(tag) if markdown_block(&tag).is_some() => {
+                collector.end_block(&current_block(&blocks));
+                blocks.pop();
+            }
+            Event::Text(text) | Event::Code(text) => collector.push_text(&text, line),
+            Event::SoftBreak | Event::HardBreak => collector.push_text("\n", line),
+            _ => {}
+        }
+    }
+    flush_html(&mut html, &mut collector, &blocks);
+    collector.end_block(&current_block(&blocks));
+    collector.segments
+}
+
+/// Name of the html element corresponding to markdown block tags, None for inline tags.
+fn markdown_block(tag: &Tag) -> Option<String> {
+    let element = match tag {
+        Tag::Paragraph => "p".to_string(),
+        Tag::Heading(level, _, _) => level.to_string(),
+        Tag::BlockQuote => "blockquote".to_string(),
+        Tag::CodeBlock(CodeBlockKind::Fenced(_)) | Tag::CodeBlock(CodeBlockKind::Indented) => "pre".to_string(),
+        Tag::List(Some(_)) => "ol".to_string(),
+        Tag::List(None) => "ul".to_string(),
+        Tag::Item => "li".to_string(),
+        Tag::FootnoteDefinition(_) => "footnote".to_string(),
+        Tag::Table(_) => "table".to_string(),
+        Tag::TableHead | Tag::TableRow => "tr".to_string(),
+        Tag::TableCell => "td".to_string(),
+        _ => return None
+    };
+    Some(element)
+}
@@ -0,0 +1,245 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use flate2::read::GzDecoder;
+use sxd_document::parser;
+use sxd_xpath::nodeset::Node;
+use sxd_xpath::{Context, Factory, Value, XPath};
+
+use crate::cli::Cli;
+use crate::json_path_search::{archive_kind, ArchiveKind};
+use crate::result_printer::HitFields;
+use crate::OutputPrinter;
+
+/// Extensions of zip archives whose xml entries are searched. Xml entries of tar archives are searched too.
+const ARCHIVE_EXTENSIONS: [&str; 4] = ["zip", "jar", "war", "ear"];
+
+/// Builds the XPath of the search expression or exits if it is not valid.
+pub(crate) fn build_xpath(search_expression: &str) -> XPath {
+    match Factory::new().build(search_expression) {
+        Ok(Some(xpath)) => xpath,
+        Ok(None) => {
+            eprintln!("The XPath expression is empty");
+            process::exit(0x0001);
+        }
+        Err(e) => {
+            eprintln!("Invalid XPath expression '{}': {}", search_expression, e);
+            process::exit(0x0001);
+        }
+    }
+}
+
+/// Creates the evaluation context with the namespace bindings of the command line, given as prefix=uri.
+/// Exits if a binding is not valid.
+pub(crate) fn build_xpath_context(cli: &Cli) -> Context<'static> {
+    let mut context = Context::new();
+    for binding in &cli.namespace {
+        match binding.split_once('=') {
+            Some((prefix, uri)) => context.set_namespace(prefix, uri),
+            None => {
+                eprintln!("Invalid namespace binding '{}', expected prefix=uri", binding);
+                process::exit(0x0001);
+            }
+        }
+    }
+    context
+}
+
+pub(crate) fn process_file_with_xpath(path: PathBuf, xpath: &XPath, context: &Context, output: &mut dyn OutputPrinter) {
+    let file_name = path.to_str().unwrap();
+    let is_zip = path.extension()
+        .map(|e| ARCHIVE_EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str()))
+        .unwrap_or(false);
+    let result = match archive_kind(&path) {
+        _ if is_zip => process_zip_with_xpath(&path, xpath, context, output),
+        Some(kind @ (ArchiveKind::Tar | ArchiveKind::TarGz)) => process_tar_with_xpath(&path, kind, xpath, context, output),
+        _ => {
+            match fs::read_to_string(&path) {
+                Ok(xml) => evaluate_and_print(file_name, None, &xml, xpath, context, output),
+                Err(e) => output.err_output(format!("Could not read '{}': {}", file_name, e).as_str())
+            }
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        output.err_output(format!("{} :: {}", file_name, e).as_str());
+    }
+}
+
+fn process_entry_with_xpath(file_name: &str, entry_name: &str, reader: &mut dyn Read, xpath: &XPath,
+                            context: &Context, output: &mut dyn OutputPrinter) {
+    if !entry_name.to_lowercase().ends_with(".xml") {
+        return;
+    }
+    let location = format!("{} :: {}", file_name, entry_name);
+    let mut xml = String::new();
+    match reader.read_to_string(&mut xml) {
+        Ok(_) => evaluate_and_print(&location, Some(entry_name), &xml, xpath, context, output),
+        Err(e) => output.err_output(format!("Could not read '{}': {}", location, e).as_str())
+    }
+}
+
+fn process_zip_with_xpath(path: &Path, xpath: &XPath, context: &Context,
+                          output: &mut dyn OutputPrinter) -> Result<(), String> {
+    let file_name = path.to_str().unwrap();
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    for i in 0..archive.len() {
+        match archive.by_index(i) {
+            Ok(mut entry) => {
+                let entry_name = entry.name().to_string();
+                process_entry_with_xpath(file_name, &entry_name, &mut entry, xpath, context, output);
+            }
+            Err(e) => output.err_output(format!("{} :: {}", file_name, e).as_str())
+        }
+    }
+    Ok(())
+}
+
+fn process_tar_with_xpath(path: &Path, kind: ArchiveKind, xpath: &XPath, context: &Context,
+                          output: &mut dyn OutputPrinter) -> Result<(), String> {
+    let file_name = path.to_str().unwrap();
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let reader: Box<dyn Read> = match kind {
+        ArchiveKind::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file)
+    };
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                output.err_output(format!("{} :: {}", file_name, e).as_str());
+                continue;
+            }
+        };
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        match entry.path() {
+            Ok(entry_path) => {
+                let entry_name = entry_path.to_string_lossy().to_string();
+                process_entry_with_xpath(file_name, &entry_name, &mut entry, xpath, context, output);
+            }
+            Err(e) => output.err_output(format!("{} :: {}", file_name, e).as_str())
+        }
+    }
+    Ok(())
+}
+
+/// Evaluates the XPath and prints "name :: node path :: line :: value" for every node of the result.
+/// Results which are not node sets are printed as "name :: value" unless they are false or empty.
+fn evaluate_and_print(name: &str, entry: Option<&str>, xml: &str, xpath: &XPath, context: &Context,
+                      output: &mut dyn OutputPrinter) {
+    let package = match parser::parse(xml) {
+        Ok(package) => package,
+        Err(e) => {
+            output.err_output(format!("Could not parse '{}': {:?}", name, e).as_str());
+            return;
+        }
+    };
+    let document = package.as_document();
+    match xpath.evaluate(context, document.root()) {
+        Ok(Value::Nodeset(nodes)) => {
+            // sxd_document does not track positions, so the lines are looked up in a second parse
+            let options = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+            let positions = roxmltree::Document::parse_with_options(xml, options).ok();
+            for node in nodes.document_order() {
+                let line = positions.as_ref().and_then(|doc| line_of(doc, node));
+                let fields = HitFields {
+                    entry: entry.map(|e| e.to_string()),
+                    line: line.map(u64::from),
+                    matched: Some(node_value(node)),
+                    ..HitFields::default()
+                };
+                let line = line.map(|l| l.to_string()).unwrap_or_else(|| "?".to_string());
+                output.output_hit(format!("{} :: {} :: {} :: {}", name, node_path(node), line,
+                                          node_value(node)).as_str(), fields);
+            }
+        }
+        Ok(Value::Boolean(false)) => {}
+        Ok(value) => {
+            let s = value.string();
+            if !s.is_empty() {
+                let fields = HitFields {
+                    entry: entry.map(|e| e.to_string()),
+                    matched: Some(s.clone()),
+                    ..HitFields::default()
+                };
+                output.output_hit(format!("{} :: {}", name, s).as_str(), fields);
+            }
+        }
+        Err(e) => {
+            output.err_output(format!("Could not evaluate XPath on '{}': {}", name, e).as_str());
+        }
+    }
+}
+
+fn node_value(node: Node) -> String {
+    node.string_value().split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Position of the element among the element children of its parent, starting with 0.
+fn element_index(node: Node) -> usize {
+    node.preceding_siblings().iter().filter(|n| matches!(n, Node::Element(_))).count()
+}
+
+/// Builds a readable path like /project/target[2]/@name. Indexes are only added if there are siblings with the same name.
+fn node_path(node: Node) -> String {
+    let step = match node {
+        Node::Root(_) => return "/".to_string(),
+        Node::Element(_) => {
+            let name = node.prefixed_name().unwrap_or_default();
+            let same_name = |n: &Node| matches!(n, Node::Element(_)) && n.prefixed_name().unwrap_or_default() == name;
+            let preceding = node.preceding_siblings().iter().filter(|n| same_name(n)).count();
+            let following = node.following_siblings().iter().filter(|n| same_name(n)).count();
+            if preceding + following > 0 {
+                format!("{}[{}]", name, preceding + 1)
+            } else {
+                name
+            }
+        }
+        Node::Attribute(_) => format!("@{}", node.prefixed_name().unwrap_or_default()),
+        Node::Text(_) => "text()".to_string(),
+        Node::Comment(_) => "comment()".to_string(),
+        Node::ProcessingInstruction(_) => format!("processing-instruction('{}')", node.prefixed_name().unwrap_or_default()),
+        Node::Namespace(_) => format!("namespace::{}", node.prefixed_name().unwrap_or_default()),
+    };
+    match node.parent() {
+        Some(Node::Root(_)) | None => format!("/{}", step),
+        Some(parent) => format!("{}/{}", node_path(parent), step),
+    }
+}
+
+/// Looks up the line of the node by following the element indexes from the root in the positioned document.
+/// Attributes get the line of the attribute itself, all other nodes the line of their element.
+fn line_of(doc: &roxmltree::Document, node: Node) -> Option<u32> {
+    let element = match node {
+        Node::Element(_) => node,
+        Node::Root(_) => return Some(1),
+        _ => node.parent()?,
+    };
+    let mut indexes = vec![];
+    let mut current = element;
+    while let Node::Element(_) = current {
+        indexes.push(element_index(current));
+        current = current.parent()?;
+    }
+    let mut positioned = doc.root();
+    for index in indexes.iter().rev() {
+        positioned = positioned.children().filter(|n| n.is_element()).nth(*index)?;
+    }
+    let position = match node {
+        Node::Attribute(a) => {
+            let name = a.name();
+            positioned.attributes()
+                .find(|attr| attr.name() == name.local_part() && attr.namespace() == name.namespace_uri())
+                .map(|attr| attr.range().start)
+                .unwrap_or(positioned.range().start)
+        }
+        _ => positioned.range().start
+    };
+    Some(doc.text_pos_at(position).row)
+}